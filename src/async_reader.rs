@@ -0,0 +1,43 @@
+use std::sync::{Arc, Mutex};
+
+use pcsc::Error as PcscError;
+use tokio::task;
+
+use crate::{Reader, RfidTag};
+
+/// A [`Reader`] whose blocking waits run on tokio's blocking thread pool.
+///
+/// Dropping a pending [`AsyncReader::next_card`] future does not stop the wait already running
+/// on the pool; it completes in the background and any card it finds is discarded.
+pub struct AsyncReader {
+    reader: Arc<Mutex<Reader>>,
+}
+
+impl AsyncReader {
+    pub fn new(reader: Reader) -> Self {
+        Self {
+            reader: Arc::new(Mutex::new(reader)),
+        }
+    }
+
+    /// Wait until a card is presented to the reader
+    pub async fn next_card(&mut self) -> Result<RfidTag, PcscError> {
+        let reader = Arc::clone(&self.reader);
+        task::spawn_blocking(move || {
+            let mut reader = reader.lock().map_err(|_| PcscError::InternalError)?;
+            loop {
+                if let Some(tag) = reader.get_card()? {
+                    return Ok(tag);
+                }
+            }
+        })
+        .await
+        .map_err(|_| PcscError::InternalError)?
+    }
+}
+
+impl From<Reader> for AsyncReader {
+    fn from(reader: Reader) -> Self {
+        Self::new(reader)
+    }
+}
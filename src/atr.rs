@@ -1,3 +1,4 @@
+use pcsc::Protocols;
 use thiserror::Error;
 
 const STORAGE_CARD_RID: [u8; 5] = [0xA0, 0x00, 0x00, 0x03, 0x06];
@@ -7,14 +8,25 @@ const STORAGE_CARD_RID: [u8; 5] = [0xA0, 0x00, 0x00, 0x03, 0x06];
 pub struct Unknown;
 
 /// Known NFC tag types based on their ATR
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum TagType {
     StorageCard,
     Iso14443_4,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl TagType {
+    /// Whether the card is driven with the reader's FF class storage card commands rather than
+    /// native ISO7816 application commands
+    pub fn uses_storage_commands(&self) -> bool {
+        match self {
+            TagType::StorageCard => true,
+            TagType::Iso14443_4 => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
 #[non_exhaustive]
 pub enum Standard {
@@ -35,6 +47,54 @@ pub enum Standard {
     Iso7816_10_3Wbp = 0b0001_0000,
     FeliCa = 0b0001_0001,
     LowFrequencyContactless = 0b0100_0000,
+    /// A standard byte this crate does not recognize
+    Unknown(u8),
+}
+
+impl Standard {
+    /// ISO14443 type A, any part
+    pub fn is_iso14443a(&self) -> bool {
+        matches!(
+            self,
+            Standard::Iso14443APart1 | Standard::Iso14443APart2 | Standard::Iso14443APart3
+        )
+    }
+
+    /// ISO14443 type B, any part
+    pub fn is_iso14443b(&self) -> bool {
+        matches!(
+            self,
+            Standard::Iso14443BPart1 | Standard::Iso14443BPart2 | Standard::Iso14443BPart3
+        )
+    }
+
+    /// ISO15693, any part
+    pub fn is_iso15693(&self) -> bool {
+        matches!(
+            self,
+            Standard::Iso15693Part1
+                | Standard::Iso15693Part2
+                | Standard::Iso15693Part3
+                | Standard::Iso15693Part4
+        )
+    }
+
+    /// Part number of an ISO14443 or ISO15693 standard
+    pub fn part(&self) -> Option<u8> {
+        match self {
+            Standard::Iso14443APart1 | Standard::Iso14443BPart1 | Standard::Iso15693Part1 => {
+                Some(1)
+            }
+            Standard::Iso14443APart2 | Standard::Iso14443BPart2 | Standard::Iso15693Part2 => {
+                Some(2)
+            }
+            Standard::Iso14443APart3 | Standard::Iso14443BPart3 | Standard::Iso15693Part3 => {
+                Some(3)
+            }
+            Standard::Iso15693Part4 => Some(4),
+            _ => None,
+        }
+    }
 }
 
 impl TryFrom<u8> for Standard {
@@ -64,7 +124,33 @@ impl TryFrom<u8> for Standard {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl From<Standard> for u8 {
+    fn from(value: Standard) -> Self {
+        match value {
+            Standard::NoInformation => 0b0000_0000,
+            Standard::Iso14443APart1 => 0b0000_0001,
+            Standard::Iso14443APart2 => 0b0000_0010,
+            Standard::Iso14443APart3 => 0b0000_0011,
+            Standard::Iso14443BPart1 => 0b0000_0101,
+            Standard::Iso14443BPart2 => 0b0000_0110,
+            Standard::Iso14443BPart3 => 0b0000_0111,
+            Standard::Iso15693Part1 => 0b0000_1001,
+            Standard::Iso15693Part2 => 0b0000_1010,
+            Standard::Iso15693Part3 => 0b0000_1011,
+            Standard::Iso15693Part4 => 0b0000_1100,
+            Standard::Iso7816_10I2c => 0b0000_1101,
+            Standard::Iso7816_10I2cExtended => 0b0000_1110,
+            Standard::Iso7816_10_2Wbp => 0b0000_1111,
+            Standard::Iso7816_10_3Wbp => 0b0001_0000,
+            Standard::FeliCa => 0b0001_0001,
+            Standard::LowFrequencyContactless => 0b0100_0000,
+            Standard::Unknown(ss) => ss,
+        }
+    }
+}
+
+#[allow(clippy::mistyped_literal_suffixes)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u16)]
 #[non_exhaustive]
 pub enum CardName {
@@ -129,8 +215,11 @@ pub enum CardName {
     FeliCa = 0x00_3B,
     MelexisSensorTag = 0x00_3C,
     MifareUltralightEv1 = 0x00_3D,
+    /// A card name code this crate does not recognize
+    Unknown(u16),
 }
 
+#[allow(clippy::mistyped_literal_suffixes)]
 impl TryFrom<u16> for CardName {
     type Error = Unknown;
 
@@ -202,18 +291,312 @@ impl TryFrom<u16> for CardName {
     }
 }
 
-pub fn parse_atr(atr: &[u8]) -> (Option<TagType>, Option<Standard>, Option<CardName>) {
-    match atr.get(0..5) {
+/// Broad card families sharing a command set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CardFamily {
+    /// MIFARE Classic and MIFARE Plus in security level 1
+    MifareClassic,
+    /// MIFARE Ultralight variants
+    MifareUltralight,
+    /// MIFARE DESFire. PC/SC has no card name for it, so no [`CardName`] maps here.
+    Desfire,
+    Iso15693,
+    Felica,
+    Other,
+}
+
+impl CardName {
+    pub fn family(&self) -> CardFamily {
+        match self {
+            CardName::MifareStandard1K
+            | CardName::MifareStandard4K
+            | CardName::MifareMini
+            | CardName::MifarePlusSl1_2K
+            | CardName::MifarePlusSl1_4K => CardFamily::MifareClassic,
+            CardName::MifareUltraLight
+            | CardName::MifareUltralightC
+            | CardName::MifareUltralightEv1 => CardFamily::MifareUltralight,
+            CardName::TagIt
+            | CardName::Lri512
+            | CardName::ICodeSli
+            | CardName::ICodeSl2
+            | CardName::Lri64
+            | CardName::Lri12
+            | CardName::Lri128 => CardFamily::Iso15693,
+            CardName::FeliCa => CardFamily::Felica,
+            _ => CardFamily::Other,
+        }
+    }
+}
+
+#[allow(clippy::mistyped_literal_suffixes)]
+impl From<CardName> for u16 {
+    fn from(value: CardName) -> Self {
+        match value {
+            CardName::NoInformation => 0x00_00,
+            CardName::MifareStandard1K => 0x00_01,
+            CardName::MifareStandard4K => 0x00_02,
+            CardName::MifareUltraLight => 0x00_03,
+            CardName::Sle55R => 0x00_04,
+            CardName::Sr176 => 0x00_06,
+            CardName::SriX4K => 0x00_07,
+            CardName::At88Rr020 => 0x00_08,
+            CardName::At88Sc0204Crf => 0x00_09,
+            CardName::At88Sc0808Crf => 0x00_0A,
+            CardName::At88Sc1616Crf => 0x00_0B,
+            CardName::At88Sc3216Crf => 0x00_0C,
+            CardName::At88Sc6416Crf => 0x00_0D,
+            CardName::Srf55V10P => 0x00_0E,
+            CardName::Srf55V02P => 0x00_0F,
+            CardName::Srf55V10S => 0x00_10,
+            CardName::Srf55V02S => 0x00_11,
+            CardName::TagIt => 0x00_12,
+            CardName::Lri512 => 0x00_13,
+            CardName::ICodeSli => 0x00_14,
+            CardName::TempSens => 0x00_15,
+            CardName::ICode1 => 0x00_16,
+            CardName::PicoPass2K => 0x00_17,
+            CardName::PicoPass2KS => 0x00_18,
+            CardName::PicoPass16K => 0x00_19,
+            CardName::PicoPass16Ks => 0x00_1A,
+            CardName::PicoPass16K8x2 => 0x00_1B,
+            CardName::PicoPass16Ks8x2 => 0x00_1C,
+            CardName::PicoPass32Ks16plus16 => 0x00_1D,
+            CardName::PicoPass32Ks16plus8x2 => 0x00_1E,
+            CardName::PicoPass32Ks8x2plus16 => 0x00_1F,
+            CardName::PicoPass32Ks8x2plus8x2 => 0x00_20,
+            CardName::Lri64 => 0x00_21,
+            CardName::ICodeUid => 0x00_22,
+            CardName::ICodeEpc => 0x00_23,
+            CardName::Lri12 => 0x00_24,
+            CardName::Lri128 => 0x00_25,
+            CardName::MifareMini => 0x00_26,
+            CardName::MyDMove => 0x00_27,
+            CardName::MyDNfc => 0x00_28,
+            CardName::MyDProximity2 => 0x00_29,
+            CardName::MyDProximityEnhanced => 0x00_2A,
+            CardName::MyDLight => 0x00_2B,
+            CardName::PjmStackTag => 0x00_2C,
+            CardName::PjmItemTag => 0x00_2D,
+            CardName::PjmLight => 0x00_2E,
+            CardName::JewelTag => 0x00_2F,
+            CardName::TopazNfcTag => 0x00_30,
+            CardName::At88Sc0104Crf => 0x00_31,
+            CardName::At88Sc0404Crf => 0x00_32,
+            CardName::At88Rf01C => 0x00_33,
+            CardName::At88Rf04C => 0x00_34,
+            CardName::ICodeSl2 => 0x00_35,
+            CardName::MifarePlusSl1_2K => 0x00_36,
+            CardName::MifarePlusSl1_4K => 0x00_37,
+            CardName::MifarePlusSl2_2K => 0x00_38,
+            CardName::MifarePlusSl2_4K => 0x00_39,
+            CardName::MifareUltralightC => 0x00_3A,
+            CardName::FeliCa => 0x00_3B,
+            CardName::MelexisSensorTag => 0x00_3C,
+            CardName::MifareUltralightEv1 => 0x00_3D,
+            CardName::Unknown(nn) => nn,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AtrError {
+    #[error("ATR shorter than its format bytes announce")]
+    Truncated,
+    #[error("ATR checksum mismatch: TCK {tck:#04X}, expected {expected:#04X}")]
+    ChecksumMismatch { tck: u8, expected: u8 },
+}
+
+// Where the historical bytes start and how many there are, and whether a TCK must follow them
+struct AtrLayout {
+    historical_start: usize,
+    historical_len: usize,
+    has_tck: bool,
+}
+
+impl AtrLayout {
+    // Offset just past the TCK, or past the historical bytes without one
+    fn end(&self) -> usize {
+        self.historical_start + self.historical_len + self.has_tck as usize
+    }
+}
+
+fn atr_layout(atr: &[u8]) -> Option<AtrLayout> {
+    let &t0 = atr.get(1)?;
+    let mut indicator = t0 >> 4;
+    let mut offset = 2;
+    let mut has_tck = false;
+    loop {
+        // TAi, TBi and TCi
+        offset += (indicator & 0x07).count_ones() as usize;
+        if indicator & 0x08 == 0 {
+            break;
+        }
+        let &td = atr.get(offset)?;
+        offset += 1;
+        // TCK is present unless only T=0 is indicated
+        has_tck |= td & 0x0F != 0;
+        indicator = td >> 4;
+    }
+    Some(AtrLayout {
+        historical_start: offset,
+        historical_len: (t0 & 0x0F) as usize,
+        has_tck,
+    })
+}
+
+/// The transmission protocols an ATR offers, from the T values of its TDi bytes. An ATR without
+/// TD1 implies T=0 only. Contactless readers announce T=1 for the cards they emulate, memory
+/// cards included.
+pub fn supported_protocols(atr: &[u8]) -> Protocols {
+    let mut protocols = Protocols::empty();
+    let Some(&t0) = atr.get(1) else {
+        return protocols;
+    };
+    let mut indicator = t0 >> 4;
+    let mut offset = 2;
+    if indicator & 0x08 == 0 {
+        return Protocols::T0;
+    }
+    loop {
+        offset += (indicator & 0x07).count_ones() as usize;
+        if indicator & 0x08 == 0 {
+            break;
+        }
+        let Some(&td) = atr.get(offset) else {
+            break;
+        };
+        offset += 1;
+        match td & 0x0F {
+            0 => protocols |= Protocols::T0,
+            1 => protocols |= Protocols::T1,
+            _ => {}
+        }
+        indicator = td >> 4;
+    }
+    protocols
+}
+
+/// The historical bytes of an ATR, located by walking its interface bytes. Empty if the ATR is
+/// too short to hold what its format bytes announce.
+pub fn historical_bytes(atr: &[u8]) -> &[u8] {
+    atr_layout(atr)
+        .and_then(|layout| {
+            atr.get(layout.historical_start..layout.historical_start + layout.historical_len)
+        })
+        .unwrap_or(&[])
+}
+
+/// The likely tag type and card name for an ISO14443-A SAK, per NXP AN10833. More precise
+/// than the ATR for cards the reader reports generically, but a hint only: other vendors'
+/// cards may reuse the same SAK values.
+pub fn card_from_sak(sak: u8) -> Option<(TagType, Option<CardName>)> {
+    let storage = |name| Some((TagType::StorageCard, Some(name)));
+    match sak {
+        0x00 => storage(CardName::MifareUltraLight),
+        0x08 | 0x88 => storage(CardName::MifareStandard1K),
+        0x09 => storage(CardName::MifareMini),
+        0x10 => storage(CardName::MifarePlusSl2_2K),
+        0x11 => storage(CardName::MifarePlusSl2_4K),
+        0x18 => storage(CardName::MifareStandard4K),
+        // Compliant with ISO14443-4, e.g. DESFire or MIFARE Plus in security level 3
+        _ if sak & 0x20 != 0 => Some((TagType::Iso14443_4, None)),
+        _ => None,
+    }
+}
+
+/// Historical bytes in the ISO7816-4 compact-TLV format announced by category indicator 80
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistoricalBytes {
+    /// `(tag, value)` for each object in order; tags are the high nibble of the object header
+    pub objects: Vec<(u8, Vec<u8>)>,
+}
+
+impl HistoricalBytes {
+    /// Decode historical bytes if they use compact-TLV, keeping the objects before any
+    /// malformed one
+    pub fn parse(historical_bytes: &[u8]) -> Option<Self> {
+        let (&0x80, mut rest) = historical_bytes.split_first()? else {
+            return None;
+        };
+        let mut objects = Vec::new();
+        while let Some((&header, tail)) = rest.split_first() {
+            let len = (header & 0x0F) as usize;
+            let Some(value) = tail.get(..len) else {
+                break;
+            };
+            objects.push((header >> 4, value.to_vec()));
+            rest = &tail[len..];
+        }
+        Some(Self { objects })
+    }
+
+    /// Value of the first object with `tag`
+    pub fn get(&self, tag: u8) -> Option<&[u8]> {
+        self.objects
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, value)| &value[..])
+    }
+
+    /// Tag 3
+    pub fn card_service_data(&self) -> Option<u8> {
+        self.get(0x3).and_then(|value| value.first().copied())
+    }
+
+    /// Tag 4
+    pub fn application_identifier(&self) -> Option<&[u8]> {
+        self.get(0x4)
+    }
+
+    /// Tag 6
+    pub fn pre_issuing_data(&self) -> Option<&[u8]> {
+        self.get(0x6)
+    }
+
+    /// Tag 7, one to three bytes of card capabilities
+    pub fn card_capabilities(&self) -> Option<&[u8]> {
+        self.get(0x7)
+    }
+
+    /// Tag 8, a life cycle status byte and/or status words
+    pub fn status_indicator(&self) -> Option<&[u8]> {
+        self.get(0x8)
+    }
+}
+
+/// Everything recognized from an ATR
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct AtrInfo {
+    pub tag_type: Option<TagType>,
+    pub standard: Option<Standard>,
+    pub card_name: Option<CardName>,
+    pub historical_bytes: Vec<u8>,
+    /// The historical bytes decoded, if they use compact-TLV
+    pub historical: Option<HistoricalBytes>,
+    /// The ATR as parsed
+    pub atr: Vec<u8>,
+    /// Vendor specific bytes some storage cards append after the end of the ATR structure,
+    /// i.e. after the TCK
+    pub vendor_extension: Vec<u8>,
+}
+
+pub fn parse_atr(atr: &[u8]) -> AtrInfo {
+    let (tag_type, standard, card_name) = match atr.get(0..5) {
         Some([0x3B, len, 0x80, 0x01, 0x80]) => {
             match (len, atr.get(5)) {
                 // Storage card per PC/SC spec
                 (_, Some(0x4f)) => {
                     if atr.get(7..12) == Some(&STORAGE_CARD_RID) {
-                        let standard = atr.get(13).and_then(|ss| Standard::try_from(*ss).ok());
+                        let standard = atr
+                            .get(12)
+                            .map(|&ss| Standard::try_from(ss).unwrap_or(Standard::Unknown(ss)));
                         let card_name = atr
-                            .get(14..15)
+                            .get(13..15)
                             .map(|bytes| u16::from_be_bytes(bytes.try_into().unwrap()))
-                            .and_then(|nn| CardName::try_from(nn).ok());
+                            .map(|nn| CardName::try_from(nn).unwrap_or(CardName::Unknown(nn)));
                         (Some(TagType::StorageCard), standard, card_name)
                     } else {
                         (Some(TagType::StorageCard), None, None)
@@ -228,5 +611,489 @@ pub fn parse_atr(atr: &[u8]) -> (Option<TagType>, Option<Standard>, Option<CardN
         // ISO14443-4 card per MSDN and PC/SC spec
         Some([0x3B, _len, 0x80, 0x01, _]) => (Some(TagType::Iso14443_4), None, None),
         _ => (None, None, None),
+    };
+    let historical_bytes = historical_bytes(atr).to_vec();
+    let vendor_extension = atr_layout(atr)
+        .and_then(|layout| atr.get(layout.end()..))
+        .unwrap_or_default()
+        .to_vec();
+    AtrInfo {
+        tag_type,
+        standard,
+        card_name,
+        historical: HistoricalBytes::parse(&historical_bytes),
+        historical_bytes,
+        atr: atr.to_vec(),
+        vendor_extension,
+    }
+}
+
+/// Builds the ATR a PC/SC reader reports for a contactless storage card, e.g. for test fixtures
+#[derive(Debug, Clone, Copy)]
+pub struct AtrBuilder {
+    standard: Standard,
+    card_name: CardName,
+}
+
+impl AtrBuilder {
+    pub fn new(standard: Standard, card_name: CardName) -> Self {
+        Self {
+            standard,
+            card_name,
+        }
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        // Category indicator, then the application identifier TLV
+        let mut historical = vec![0x80, 0x4F, 0x0C];
+        historical.extend(STORAGE_CARD_RID);
+        historical.push(self.standard.into());
+        historical.extend(u16::from(self.card_name).to_be_bytes());
+        historical.extend([0x00; 4]); // RFU
+
+        // TS, T0 (TD1 present), TD1 (T=0, TD2 present), TD2 (T=1)
+        let mut atr = vec![0x3B, 0x80 | historical.len() as u8, 0x80, 0x01];
+        atr.extend(historical);
+        let tck = atr[1..].iter().fold(0, |acc, b| acc ^ b);
+        atr.push(tck);
+        atr
+    }
+}
+
+/// Like [`parse_atr`], but first checks that the ATR is complete and its TCK is correct
+pub fn parse_atr_checked(atr: &[u8]) -> Result<AtrInfo, AtrError> {
+    let layout = atr_layout(atr).ok_or(AtrError::Truncated)?;
+    let end = layout.historical_start + layout.historical_len;
+    if layout.has_tck {
+        let &tck = atr.get(end).ok_or(AtrError::Truncated)?;
+        // TCK makes the XOR of every byte from T0 through TCK zero
+        let expected = atr[1..end].iter().fold(0, |acc, b| acc ^ b);
+        if tck != expected {
+            return Err(AtrError::ChecksumMismatch { tck, expected });
+        }
+    } else if atr.len() < end {
+        return Err(AtrError::Truncated);
+    }
+    Ok(parse_atr(atr))
+}
+
+// FSCI to maximum frame size, ISO14443-4 section 5.2.3; codes from 9 up are RFU
+const FSC_BYTES: [usize; 9] = [16, 24, 32, 40, 48, 64, 96, 128, 256];
+
+#[derive(Debug, Error)]
+pub enum AtsError {
+    #[error("ATS length byte {tl} does not match its {len} bytes")]
+    LengthMismatch { tl: u8, len: usize },
+    #[error("ATS shorter than its format byte announces")]
+    Truncated,
+}
+
+/// An ISO14443-4 answer to select, as returned by [`RfidTag::get_ats`](crate::RfidTag::get_ats)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ats {
+    /// Frame size integer from T0
+    pub fsci: u8,
+    /// Supported bit rates
+    pub ta: Option<u8>,
+    /// Frame waiting and start-up frame guard time integers
+    pub tb: Option<u8>,
+    /// Supported frame options
+    pub tc: Option<u8>,
+    pub historical_bytes: Vec<u8>,
+}
+
+impl Ats {
+    /// Parse an ATS starting with its TL length byte
+    pub fn parse(ats: &[u8]) -> Result<Self, AtsError> {
+        let (&tl, rest) = ats.split_first().ok_or(AtsError::Truncated)?;
+        if tl as usize != ats.len() {
+            return Err(AtsError::LengthMismatch { tl, len: ats.len() });
+        }
+        // A lone TL means no T0, so every default applies
+        let Some((&t0, mut rest)) = rest.split_first() else {
+            return Ok(Self {
+                fsci: 2,
+                ta: None,
+                tb: None,
+                tc: None,
+                historical_bytes: Vec::new(),
+            });
+        };
+        let mut interface_byte = |present: u8| {
+            if t0 & present == 0 {
+                return Ok(None);
+            }
+            let (&byte, tail) = rest.split_first().ok_or(AtsError::Truncated)?;
+            rest = tail;
+            Ok(Some(byte))
+        };
+        let ta = interface_byte(0x10)?;
+        let tb = interface_byte(0x20)?;
+        let tc = interface_byte(0x40)?;
+        Ok(Self {
+            fsci: t0 & 0x0F,
+            ta,
+            tb,
+            tc,
+            historical_bytes: rest.to_vec(),
+        })
+    }
+
+    /// Largest frame the card accepts, in bytes; RFU codes are read as 256
+    pub fn max_frame_size(&self) -> usize {
+        FSC_BYTES[(self.fsci as usize).min(FSC_BYTES.len() - 1)]
+    }
+
+    /// Frame waiting time integer, 4 if TB is absent
+    pub fn fwi(&self) -> u8 {
+        self.tb.map_or(4, |tb| tb >> 4)
+    }
+
+    /// Start-up frame guard time integer, 0 if TB is absent
+    pub fn sfgi(&self) -> u8 {
+        self.tb.map_or(0, |tb| tb & 0x0F)
+    }
+
+    pub fn supports_nad(&self) -> bool {
+        self.tc.is_some_and(|tc| tc & 0x01 != 0)
+    }
+
+    /// Whether the card accepts a CID, which it does by default
+    pub fn supports_cid(&self) -> bool {
+        self.tc.is_none_or(|tc| tc & 0x02 != 0)
+    }
+}
+
+/// Card schemes identified by the registered application provider (RID) of an AID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PaymentNetwork {
+    Visa,
+    Mastercard,
+    AmericanExpress,
+    Discover,
+    Jcb,
+    UnionPay,
+    Interac,
+}
+
+impl PaymentNetwork {
+    /// Match the 5 byte RID at the start of an AID
+    pub fn from_aid(aid: &[u8]) -> Option<Self> {
+        match aid.get(0..5)? {
+            [0xA0, 0x00, 0x00, 0x00, 0x03] => Some(PaymentNetwork::Visa),
+            [0xA0, 0x00, 0x00, 0x00, 0x04] => Some(PaymentNetwork::Mastercard),
+            [0xA0, 0x00, 0x00, 0x00, 0x25] => Some(PaymentNetwork::AmericanExpress),
+            [0xA0, 0x00, 0x00, 0x01, 0x52] => Some(PaymentNetwork::Discover),
+            [0xA0, 0x00, 0x00, 0x00, 0x65] => Some(PaymentNetwork::Jcb),
+            [0xA0, 0x00, 0x00, 0x03, 0x33] => Some(PaymentNetwork::UnionPay),
+            [0xA0, 0x00, 0x00, 0x02, 0x77] => Some(PaymentNetwork::Interac),
+            _ => None,
+        }
+    }
+}
+
+/// Look for a payment AID in the application identifier of compact-TLV historical bytes.
+///
+/// This is only a hint: most payment cards do not advertise an AID in their ATS, and a positive
+/// identification needs a SELECT of the payment application (or of the PPSE) on the card.
+pub fn payment_network(historical_bytes: &[u8]) -> Option<PaymentNetwork> {
+    HistoricalBytes::parse(historical_bytes)?
+        .application_identifier()
+        .and_then(PaymentNetwork::from_aid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn storage_command_family() {
+        assert!(TagType::StorageCard.uses_storage_commands());
+        assert!(!TagType::Iso14443_4.uses_storage_commands());
+    }
+
+    #[test]
+    fn payment_aid_in_historical_bytes() {
+        // Visa credit AID advertised in the ATS
+        let visa = [0x80, 0x47, 0xA0, 0x00, 0x00, 0x00, 0x03, 0x10, 0x10];
+        assert_eq!(payment_network(&visa), Some(PaymentNetwork::Visa));
+        // MIFARE 1K: PC/SC registered application, not a payment card
+        let mifare = [
+            0x80, 0x4F, 0x0C, 0xA0, 0x00, 0x00, 0x03, 0x06, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x00,
+        ];
+        assert_eq!(payment_network(&mifare), None);
+        // Mastercard ATS without an application identifier
+        let mastercard = [0x80, 0x31, 0x80, 0x66, 0xB0, 0x84, 0x12, 0x01, 0x6E, 0x01];
+        assert_eq!(payment_network(&mastercard), None);
+        assert_eq!(payment_network(b"YubikeyNEOr3"), None);
+    }
+
+    #[test]
+    fn storage_card_vendor_extension() {
+        let mut atr = AtrBuilder::new(Standard::Iso14443APart3, CardName::MifareStandard1K).build();
+        assert!(parse_atr(&atr).vendor_extension.is_empty());
+
+        // Three vendor bytes after the TCK
+        atr.extend([0x4E, 0x58, 0x01]);
+        let info = parse_atr_checked(&atr).unwrap();
+        assert_eq!(info.vendor_extension, [0x4E, 0x58, 0x01]);
+        assert_eq!(info.tag_type, Some(TagType::StorageCard));
+        assert_eq!(info.standard, Some(Standard::Iso14443APart3));
+        assert_eq!(info.card_name, Some(CardName::MifareStandard1K));
+    }
+
+    #[test]
+    fn iso14443_4_historical_bytes() {
+        // Yubikey NEO
+        let atr = [
+            0x3B, 0x8C, 0x80, 0x01, 0x59, 0x75, 0x62, 0x69, 0x6B, 0x65, 0x79, 0x4E, 0x45, 0x4F,
+            0x72, 0x33, 0x58,
+        ];
+        assert_eq!(historical_bytes(&atr), b"YubikeyNEOr3");
+        // MIFARE DESFire EV1
+        assert_eq!(
+            historical_bytes(&[0x3B, 0x81, 0x80, 0x01, 0x80, 0x80]),
+            &[0x80]
+        );
+    }
+
+    #[test]
+    fn historical_bytes_after_interface_bytes() {
+        let atr = [
+            0x3B, 0x95, 0x13, 0x81, 0x01, 0x80, 0x73, 0xFF, 0x01, 0x00, 0x0B,
+        ];
+        assert_eq!(historical_bytes(&atr), &[0x80, 0x73, 0xFF, 0x01, 0x00]);
+        assert_eq!(historical_bytes(&[0x3B, 0x02, 0x14, 0x50]), &[0x14, 0x50]);
+        assert!(historical_bytes(&[0x3B, 0x8C, 0x80, 0x01, 0x59]).is_empty());
+    }
+
+    #[test]
+    fn protocols_from_td_bytes() {
+        // Contactless storage card: TD1 T=0, TD2 T=1
+        let built = AtrBuilder::new(Standard::Iso14443APart3, CardName::MifareStandard1K).build();
+        assert_eq!(supported_protocols(&built), Protocols::T0 | Protocols::T1);
+        // TD1 T=1 only
+        let atr = [
+            0x3B, 0x95, 0x13, 0x81, 0x01, 0x80, 0x73, 0xFF, 0x01, 0x00, 0x0B,
+        ];
+        assert_eq!(supported_protocols(&atr), Protocols::T1);
+        // No TD1: T=0 implied
+        assert_eq!(
+            supported_protocols(&[0x3B, 0x02, 0x14, 0x50]),
+            Protocols::T0
+        );
+        assert!(supported_protocols(&[]).is_empty());
+    }
+
+    #[test]
+    fn builder_matches_captured_atr() {
+        // MIFARE Classic 1K on an ACR122U
+        let captured = [
+            0x3B, 0x8F, 0x80, 0x01, 0x80, 0x4F, 0x0C, 0xA0, 0x00, 0x00, 0x03, 0x06, 0x03, 0x00,
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x6A,
+        ];
+        let built = AtrBuilder::new(Standard::Iso14443APart3, CardName::MifareStandard1K).build();
+        assert_eq!(built, captured);
+    }
+
+    #[test]
+    fn mifare_classic_1k() {
+        let atr = AtrBuilder::new(Standard::Iso14443APart3, CardName::MifareStandard1K).build();
+        let info = parse_atr(&atr);
+        assert_eq!(info.tag_type, Some(TagType::StorageCard));
+        assert_eq!(info.standard, Some(Standard::Iso14443APart3));
+        assert_eq!(info.card_name, Some(CardName::MifareStandard1K));
+        assert_eq!(info.historical_bytes, atr[4..19].to_vec());
+    }
+
+    #[test]
+    fn storage_cards_round_trip() {
+        for (standard, card_name) in [
+            (Standard::Iso14443APart3, CardName::MifareStandard4K),
+            (Standard::Iso14443APart3, CardName::MifareUltralightC),
+            (Standard::Iso15693Part3, CardName::ICodeSli),
+            (Standard::FeliCa, CardName::FeliCa),
+        ] {
+            let atr = AtrBuilder::new(standard, card_name).build();
+            let info = parse_atr_checked(&atr).unwrap();
+            assert_eq!(info.standard, Some(standard));
+            assert_eq!(info.card_name, Some(card_name));
+        }
+    }
+
+    #[test]
+    fn unrecognized_card_name() {
+        let atr = AtrBuilder::new(Standard::Iso14443APart3, CardName::Unknown(0x00_FF)).build();
+        let info = parse_atr(&atr);
+        assert_eq!(info.card_name, Some(CardName::Unknown(0x00_FF)));
+        assert_eq!(info.standard, Some(Standard::Iso14443APart3));
+        let atr = AtrBuilder::new(Standard::Unknown(0x20), CardName::MifareMini).build();
+        assert_eq!(parse_atr(&atr).standard, Some(Standard::Unknown(0x20)));
+    }
+
+    #[test]
+    fn card_name_codes_round_trip() {
+        for code in 0x00_00..=0x00_FF {
+            let card_name = CardName::try_from(code).unwrap_or(CardName::Unknown(code));
+            assert_eq!(u16::from(card_name), code);
+        }
+        for code in 0..=u8::MAX {
+            let standard = Standard::try_from(code).unwrap_or(Standard::Unknown(code));
+            assert_eq!(u8::from(standard), code);
+        }
+    }
+
+    #[test]
+    fn checked_tck() {
+        let mut atr = AtrBuilder::new(Standard::Iso14443APart3, CardName::MifareStandard1K).build();
+        assert_eq!(parse_atr_checked(&atr).unwrap(), parse_atr(&atr));
+        atr[19] = 0x6B;
+        assert!(matches!(
+            parse_atr_checked(&atr),
+            Err(AtrError::ChecksumMismatch {
+                tck: 0x6B,
+                expected: 0x6A
+            })
+        ));
+        assert!(matches!(
+            parse_atr_checked(&atr[..19]),
+            Err(AtrError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn checked_without_tck() {
+        // Only T=0 indicated, so there is no TCK
+        assert!(parse_atr_checked(&[0x3B, 0x02, 0x14, 0x50]).is_ok());
+        assert!(matches!(
+            parse_atr_checked(&[0x3B, 0x02, 0x14]),
+            Err(AtrError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn compact_tlv_historical_bytes() {
+        // YubiKey 5 NFC: card capabilities, then the card issuer's data "YubiKey"
+        let yubikey = [
+            0x80, 0x73, 0xC0, 0x21, 0xC0, 0x57, 0x59, 0x75, 0x62, 0x69, 0x4B, 0x65, 0x79,
+        ];
+        let historical = HistoricalBytes::parse(&yubikey).unwrap();
+        assert_eq!(
+            historical.card_capabilities(),
+            Some(&[0xC0, 0x21, 0xC0][..])
+        );
+        assert_eq!(historical.get(0x5), Some(&b"YubiKey"[..]));
+        assert_eq!(historical.pre_issuing_data(), None);
+
+        // JCOP: card service data, pre-issuing data and status indicator
+        let jcop = [
+            0x80, 0x31, 0x80, 0x65, 0xB0, 0x83, 0x11, 0x00, 0xAC, 0x83, 0x00, 0x90, 0x00,
+        ];
+        let historical = HistoricalBytes::parse(&jcop).unwrap();
+        assert_eq!(historical.card_service_data(), Some(0x80));
+        assert_eq!(
+            historical.pre_issuing_data(),
+            Some(&[0xB0, 0x83, 0x11, 0x00, 0xAC][..])
+        );
+        assert_eq!(historical.status_indicator(), Some(&[0x00, 0x90, 0x00][..]));
+
+        // Not compact-TLV
+        assert_eq!(HistoricalBytes::parse(&[0x4A, 0x43, 0x4F, 0x50]), None);
+        // Truncated object dropped, earlier ones kept
+        let truncated = HistoricalBytes::parse(&[0x80, 0x31, 0x80, 0x73, 0xC0]).unwrap();
+        assert_eq!(truncated.objects, vec![(0x3, vec![0x80])]);
+    }
+
+    #[test]
+    fn desfire_ev1_ats() {
+        let ats = Ats::parse(&[0x06, 0x75, 0x77, 0x81, 0x02, 0x80]).unwrap();
+        assert_eq!(ats.max_frame_size(), 64);
+        assert_eq!(
+            (ats.ta, ats.tb, ats.tc),
+            (Some(0x77), Some(0x81), Some(0x02))
+        );
+        assert_eq!((ats.fwi(), ats.sfgi()), (8, 1));
+        assert!(ats.supports_cid());
+        assert!(!ats.supports_nad());
+        assert_eq!(ats.historical_bytes, [0x80]);
+
+        let bare = Ats::parse(&[0x01]).unwrap();
+        assert_eq!((bare.max_frame_size(), bare.fwi()), (32, 4));
+        assert!(matches!(
+            Ats::parse(&[0x03, 0x70, 0x77]),
+            Err(AtsError::Truncated)
+        ));
+        assert!(matches!(
+            Ats::parse(&[0x06, 0x75, 0x77]),
+            Err(AtsError::LengthMismatch { tl: 6, len: 3 })
+        ));
+    }
+
+    #[test]
+    fn standard_families_and_parts() {
+        assert!(Standard::Iso14443APart3.is_iso14443a());
+        assert!(!Standard::Iso14443APart3.is_iso14443b());
+        assert!(Standard::Iso14443BPart1.is_iso14443b());
+        assert!(Standard::Iso15693Part4.is_iso15693());
+        assert!(!Standard::FeliCa.is_iso15693());
+        assert_eq!(Standard::Iso14443APart3.part(), Some(3));
+        assert_eq!(Standard::Iso14443BPart2.part(), Some(2));
+        assert_eq!(Standard::Iso15693Part4.part(), Some(4));
+        assert_eq!(Standard::Iso7816_10I2c.part(), None);
+        assert_eq!(Standard::Unknown(0x42).part(), None);
+    }
+
+    #[test]
+    fn card_type_from_sak() {
+        assert_eq!(
+            card_from_sak(0x08),
+            Some((TagType::StorageCard, Some(CardName::MifareStandard1K)))
+        );
+        assert_eq!(
+            card_from_sak(0x18),
+            Some((TagType::StorageCard, Some(CardName::MifareStandard4K)))
+        );
+        assert_eq!(card_from_sak(0x20), Some((TagType::Iso14443_4, None)));
+        assert_eq!(card_from_sak(0x01), None);
+    }
+
+    #[test]
+    fn card_families() {
+        assert_eq!(
+            CardName::MifareStandard4K.family(),
+            CardFamily::MifareClassic
+        );
+        assert_eq!(
+            CardName::MifarePlusSl1_2K.family(),
+            CardFamily::MifareClassic
+        );
+        assert_eq!(
+            CardName::MifareUltralightEv1.family(),
+            CardFamily::MifareUltralight
+        );
+        assert_eq!(CardName::ICodeSli.family(), CardFamily::Iso15693);
+        assert_eq!(CardName::FeliCa.family(), CardFamily::Felica);
+        assert_eq!(CardName::JewelTag.family(), CardFamily::Other);
+        assert_eq!(CardName::Unknown(0x1234).family(), CardFamily::Other);
+    }
+
+    #[test]
+    fn unrecognized_atr() {
+        assert_eq!(parse_atr(&[0x3B, 0x02, 0x14, 0x50]).tag_type, None);
+        assert_eq!(parse_atr(&[]), AtrInfo::default());
+    }
+
+    #[test]
+    fn card_name_as_map_key() {
+        let mut sector_counts = HashMap::new();
+        sector_counts.insert(CardName::MifareStandard1K, 16);
+        sector_counts.insert(CardName::MifareStandard4K, 40);
+        assert_eq!(sector_counts.get(&CardName::MifareStandard4K), Some(&40));
+        assert_eq!(sector_counts.get(&CardName::MifareMini), None);
+        assert_eq!(Standard::Iso14443APart3, Standard::Iso14443APart3);
+        assert_ne!(TagType::StorageCard, TagType::Iso14443_4);
     }
 }
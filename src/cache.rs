@@ -0,0 +1,107 @@
+//! Caching data read from cards that are presented repeatedly
+
+use std::collections::HashMap;
+
+use crate::command::PcscCodecError;
+use crate::transport::ApduTransport;
+use crate::RfidTag;
+
+/// A newly presented card, with the data cached for its UID if any
+pub struct CachedCard<'c, V> {
+    pub tag: RfidTag,
+    pub uid: Vec<u8>,
+    pub cached: Option<&'c V>,
+}
+
+/// User data remembered per card UID.
+///
+/// Random UIDs change on every activation, so cards presenting one are never looked up.
+#[derive(Debug, Clone)]
+pub struct CardCache<V> {
+    entries: HashMap<Vec<u8>, V>,
+}
+
+impl<V> Default for CardCache<V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<V> CardCache<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, uid: &[u8]) -> Option<&V> {
+        self.entries.get(uid)
+    }
+
+    pub fn insert(&mut self, uid: Vec<u8>, value: V) -> Option<V> {
+        self.entries.insert(uid, value)
+    }
+
+    pub fn remove(&mut self, uid: &[u8]) -> Option<V> {
+        self.entries.remove(uid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Read the tag's UID and return it with any data cached for it
+    pub fn lookup<T: ApduTransport>(
+        &self,
+        tag: &RfidTag<T>,
+    ) -> Result<(Vec<u8>, Option<&V>), PcscCodecError> {
+        let info = tag.uid_info()?;
+        let cached = if info.random {
+            None
+        } else {
+            self.get(&info.uid)
+        };
+        Ok((info.uid, cached))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn hit_and_miss() {
+        let mut cache = CardCache::new();
+        cache.insert(vec![0x04, 0xA2, 0x3B, 0x11], "visitor 17");
+
+        let tag = RfidTag::with_transport(
+            MockTransport::new([vec![0x04, 0xA2, 0x3B, 0x11, 0x90, 0x00]]),
+            &[],
+        );
+        let (uid, cached) = cache.lookup(&tag).unwrap();
+        assert_eq!(uid, [0x04, 0xA2, 0x3B, 0x11]);
+        assert_eq!(cached, Some(&"visitor 17"));
+
+        let tag = RfidTag::with_transport(
+            MockTransport::new([vec![0x04, 0xFF, 0x00, 0x01, 0x90, 0x00]]),
+            &[],
+        );
+        assert_eq!(cache.lookup(&tag).unwrap().1, None);
+    }
+
+    #[test]
+    fn random_uid_never_hits() {
+        let mut cache = CardCache::new();
+        cache.insert(vec![0x08, 0x5C, 0x91, 0xE0], ());
+        let tag = RfidTag::with_transport(
+            MockTransport::new([vec![0x08, 0x5C, 0x91, 0xE0, 0x90, 0x00]]),
+            &[],
+        );
+        assert_eq!(cache.lookup(&tag).unwrap().1, None);
+    }
+}
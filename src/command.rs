@@ -1,20 +1,107 @@
+use std::mem;
+use std::sync::OnceLock;
+
 use pcsc::Error as PcscError;
 use thiserror::Error;
 
+use crate::model::ReaderModel;
+use crate::tlv::TlvIter;
+
 #[derive(Debug, Error)]
 pub enum PcscCodecError {
     #[error("PC/SC error")]
     Pcsc(#[source] PcscError),
     #[error("Not enough bytes")]
     TooShort,
+    #[error("Response of {len} bytes, expected at least {min_len}")]
+    ResponseTooShort { len: usize, min_len: usize },
     #[error("Byte length exceeded")]
     TooLong,
+    #[error("Lc of 0 is not allowed")]
+    ZeroLc,
     #[error("Not a PC/SC storage card command")]
     WrongClass,
     #[error("Unknown PC/SC command")]
     UnknownIns,
     #[error("General Authenticate Version not supported")]
     UnknownGeneralAuthenticateVersion,
+    #[error("Card returned status {}", describe_status(.sw, .info))]
+    StatusError {
+        sw: PcscStatusWords,
+        info: Option<PcscErrorCodeInfo>,
+    },
+    #[error("Address out of range for this card")]
+    AddressOutOfRange,
+    #[error("Not supported by reader {0:?}")]
+    UnsupportedReader(ReaderModel),
+    #[error("Not supported by this card")]
+    UnsupportedCard,
+    #[error("Card failed to authenticate")]
+    AuthenticationFailed,
+    #[error("Failed at block {block}")]
+    Block {
+        block: u8,
+        #[source]
+        source: Box<PcscCodecError>,
+    },
+}
+
+impl PcscCodecError {
+    /// Build a status error for a failed command, decoding the status words against its INS
+    pub fn status(sw: PcscStatusWords, ins: u8) -> Self {
+        PcscCodecError::StatusError {
+            sw,
+            info: sw.extra_info(ins),
+        }
+    }
+
+    /// Where the failure arose, e.g. to decide whether a retry can help
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            PcscCodecError::Pcsc(_) | PcscCodecError::ResponseTooShort { .. } => {
+                ErrorCategory::Transport
+            }
+            PcscCodecError::TooShort
+            | PcscCodecError::TooLong
+            | PcscCodecError::ZeroLc
+            | PcscCodecError::WrongClass
+            | PcscCodecError::UnknownIns
+            | PcscCodecError::UnknownGeneralAuthenticateVersion
+            | PcscCodecError::AddressOutOfRange
+            | PcscCodecError::UnsupportedReader(_) => ErrorCategory::Encoding,
+            PcscCodecError::StatusError { .. }
+            | PcscCodecError::UnsupportedCard
+            | PcscCodecError::AuthenticationFailed => ErrorCategory::Card,
+            PcscCodecError::Block { source, .. } => source.category(),
+        }
+    }
+}
+
+/// Broad kind of a [`PcscCodecError`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// The reader or the link to the card failed; worth retrying
+    Transport,
+    /// The command or response could not be encoded or decoded, or was refused before sending;
+    /// retrying the same command fails the same way
+    Encoding,
+    /// The card answered but refused the command
+    Card,
+}
+
+impl From<PcscError> for PcscCodecError {
+    fn from(error: PcscError) -> Self {
+        PcscCodecError::Pcsc(error)
+    }
+}
+
+// Raw SW1 SW2 in hex, then the command specific meaning if known or else the general one
+fn describe_status(sw: &PcscStatusWords, info: &Option<PcscErrorCodeInfo>) -> String {
+    let [sw1, sw2] = sw.to_bytes();
+    match info {
+        Some(info) => format!("{sw1:02X} {sw2:02X} ({})", info.description()),
+        None => format!("{sw1:02X} {sw2:02X} ({})", sw.description()),
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -50,6 +137,168 @@ impl From<KeyType> for u8 {
     }
 }
 
+/// Data objects selectable through the Get Data P1 byte
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GetDataType {
+    Uid,
+    HistoricalBytes,
+    /// Shares P1 with the historical bytes; many readers (e.g. ACS) return the full ATS for it
+    Ats,
+    /// Vendor extension, not answered by every reader
+    Atqa,
+    /// Vendor extension, not answered by every reader
+    Sak,
+}
+
+impl GetDataType {
+    pub fn p1(&self) -> u8 {
+        match self {
+            GetDataType::Uid => 0x00,
+            GetDataType::HistoricalBytes | GetDataType::Ats => 0x01,
+            GetDataType::Atqa => 0xF0,
+            GetDataType::Sak => 0xF1,
+        }
+    }
+}
+
+/// P1 and P2 of the Get Data command reading the UID, `00 00` per PC/SC unless a reader
+/// needs otherwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GetDataParams {
+    pub p1: u8,
+    pub p2: u8,
+    /// The reader appends the SAK and ATQA to the UID, so split them off when well formed; see
+    /// [`UidDetails::split`]. Off by default, since a plain UID can pass for one carrying them.
+    pub split_sak_atqa: bool,
+}
+
+/// Whose key a Load Keys command carries, the top bit of its key structure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyStructure {
+    /// A key the reader presents to cards, e.g. a MIFARE sector key
+    #[default]
+    CardKey,
+    /// A key protecting transmission between host and reader
+    ReaderKey,
+}
+
+/// Load Keys key structure, sent in P1. The default is a card key held in volatile memory,
+/// lost when the reader powers off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LoadKeyOptions {
+    pub structure: KeyStructure,
+    /// Persist the key in the reader's non-volatile memory
+    pub into_nonvolatile: bool,
+}
+
+impl LoadKeyOptions {
+    pub fn p1(&self) -> u8 {
+        let structure = match self.structure {
+            KeyStructure::CardKey => 0x00,
+            KeyStructure::ReaderKey => 0x80,
+        };
+        let memory = if self.into_nonvolatile { 0x20 } else { 0x00 };
+        structure | memory
+    }
+}
+
+/// ISO7816-4 SELECT selection modes, sent in P1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectMode {
+    /// MF, DF or EF by file identifier
+    FileId,
+    ChildDf,
+    /// EF under the current DF
+    ChildEf,
+    /// Parent of the current DF; sent without an identifier
+    ParentDf,
+    /// DF name, i.e. an application identifier
+    DfName,
+    PathFromMf,
+    PathFromCurrentDf,
+}
+
+impl SelectMode {
+    pub fn p1(&self) -> u8 {
+        match self {
+            SelectMode::FileId => 0x00,
+            SelectMode::ChildDf => 0x01,
+            SelectMode::ChildEf => 0x02,
+            SelectMode::ParentDf => 0x03,
+            SelectMode::DfName => 0x04,
+            SelectMode::PathFromMf => 0x08,
+            SelectMode::PathFromCurrentDf => 0x09,
+        }
+    }
+}
+
+/// A UID returned by Get Data
+#[derive(Debug, Clone, PartialEq)]
+pub struct UidInfo {
+    /// 4, 7 or 10 bytes for ISO14443-3 cards
+    pub uid: Vec<u8>,
+    /// Single-size UID regenerated by the card on every activation
+    pub random: bool,
+}
+
+impl From<&[u8]> for UidInfo {
+    fn from(uid: &[u8]) -> Self {
+        UidInfo {
+            uid: uid.to_vec(),
+            random: uid.len() == 4 && uid[0] == 0x08,
+        }
+    }
+}
+
+/// A Get Data UID along with the SAK and ATQA some readers append to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct UidDetails {
+    pub uid: UidInfo,
+    pub sak: Option<u8>,
+    /// As usually written, most significant byte first, e.g. `00 44`
+    pub atqa: Option<[u8; 2]>,
+}
+
+impl UidDetails {
+    /// All of `data` as the UID, without SAK or ATQA
+    pub fn uid_only(data: &[u8]) -> Self {
+        UidDetails {
+            uid: UidInfo::from(data),
+            sak: None,
+            atqa: None,
+        }
+    }
+
+    /// Split off a trailing SAK and ATQA when they are well formed per ISO14443-3 and the
+    /// ATQA announces the size of the bytes before them; otherwise it is all UID. Only for
+    /// readers known to append them: some 7 byte UIDs look like a 4 byte one followed by both.
+    pub fn split(data: &[u8]) -> Self {
+        if let Some((uid, &[sak, atqa_msb, atqa_lsb])) = data.split_last_chunk::<3>() {
+            let atqa = [atqa_msb, atqa_lsb];
+            if sak & 0x04 == 0 && atqa_announces_uid_len(atqa, uid.len()) {
+                return UidDetails {
+                    uid: UidInfo::from(uid),
+                    sak: Some(sak),
+                    atqa: Some(atqa),
+                };
+            }
+        }
+        Self::uid_only(data)
+    }
+}
+
+// Whether an ATQA has its RFU bits clear, exactly one bit frame anticollision bit set and a
+// UID size matching `uid_len`
+fn atqa_announces_uid_len([msb, lsb]: [u8; 2], uid_len: usize) -> bool {
+    let size = match uid_len {
+        4 => 0b00,
+        7 => 0b01,
+        10 => 0b10,
+        _ => return false,
+    };
+    msb & 0xF0 == 0 && lsb & 0x20 == 0 && (lsb & 0x1F).count_ones() == 1 && lsb >> 6 == size
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum PcscInstruction {
     GetData {
@@ -69,15 +318,44 @@ pub enum PcscInstruction {
     ManageSession {
         data: Vec<u8>,
     },
+    /// An Le above 255 is sent in extended form, for readers and cards supporting it
     ReadBinary {
-        le: u8,
+        le: u16,
     },
     UpdateBinary {
         data: Vec<u8>,
     },
+    /// Raw frame passed through to the card, e.g. an ISO15693 request
+    DirectTransmit {
+        data: Vec<u8>,
+    },
+    /// ISO7816-4 SELECT, sent to the card itself with class 00
+    Select {
+        data: Vec<u8>,
+    },
 }
 
-#[derive(Debug, PartialEq)]
+impl PcscInstruction {
+    /// Whether the instruction can change state on the card or reader.
+    ///
+    /// Verify counts as mutating since a failed attempt consumes a retry, and Manage Session
+    /// since a transparent session can carry arbitrary card commands.
+    pub fn is_mutating(&self) -> bool {
+        match self {
+            PcscInstruction::UpdateBinary { .. }
+            | PcscInstruction::LoadKeys { .. }
+            | PcscInstruction::Verify { .. }
+            | PcscInstruction::ManageSession { .. }
+            | PcscInstruction::DirectTransmit { .. } => true,
+            PcscInstruction::GetData { .. }
+            | PcscInstruction::ReadBinary { .. }
+            | PcscInstruction::GeneralAuthenticate { .. }
+            | PcscInstruction::Select { .. } => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct PcscCommand {
     ins: PcscInstruction,
     p1: u8,
@@ -85,13 +363,175 @@ pub struct PcscCommand {
 }
 
 impl PcscCommand {
-    pub const MIN_LENGTH: usize = 5; // class + ins + p1 + p2 + le/lc
-    pub const MAX_LENGTH: usize = 5 + u8::MAX as usize;
+    pub const MIN_LENGTH: usize = 4; // class + ins + p1 + p2
+    pub const MAX_LENGTH: usize = 6 + u8::MAX as usize; // header + Lc + data + Le
 
     pub fn new(ins: PcscInstruction, p1: u8, p2: u8) -> Self {
         Self { ins, p1, p2 }
     }
 
+    pub fn get_data(data_type: GetDataType) -> Self {
+        Self::new(PcscInstruction::GetData { le: 0 }, data_type.p1(), 0x00)
+    }
+
+    /// Get Data with explicit P1 and P2
+    pub fn get_data_with_params(params: GetDataParams) -> Self {
+        Self::new(PcscInstruction::GetData { le: 0 }, params.p1, params.p2)
+    }
+
+    pub fn read_binary(address: u16, le: u8) -> Self {
+        let [p1, p2] = address.to_be_bytes();
+        Self::new(PcscInstruction::ReadBinary { le: le.into() }, p1, p2)
+    }
+
+    /// Read Binary with an extended length Le, reading up to 65535 bytes in one APDU
+    pub fn read_binary_extended(address: u16, le: u16) -> Self {
+        let [p1, p2] = address.to_be_bytes();
+        Self::new(PcscInstruction::ReadBinary { le }, p1, p2)
+    }
+
+    /// Read Binary from the EF with short file identifier `sfi` (1-30), carried in P1 with bit 8
+    /// set, starting `offset` bytes in
+    pub fn read_binary_sfi(sfi: u8, offset: u8, le: u8) -> Result<Self, PcscCodecError> {
+        if !(1..=30).contains(&sfi) {
+            return Err(PcscCodecError::AddressOutOfRange);
+        }
+        Ok(Self::new(
+            PcscInstruction::ReadBinary { le: le.into() },
+            0x80 | sfi,
+            offset,
+        ))
+    }
+
+    /// Load a MIFARE key into the reader's volatile key slot `slot`
+    pub fn load_key(slot: u8, key: [u8; 6]) -> Self {
+        Self::load_key_with_options(slot, key, LoadKeyOptions::default())
+    }
+
+    /// Like [`PcscCommand::load_key`], choosing the key structure and memory with `options`
+    pub fn load_key_with_options(slot: u8, key: [u8; 6], options: LoadKeyOptions) -> Self {
+        Self::new(
+            PcscInstruction::LoadKeys { data: key.to_vec() },
+            options.p1(),
+            slot,
+        )
+    }
+
+    pub fn general_authenticate(address: u16, key_type: KeyType, key_id: u8) -> Self {
+        Self::new(
+            PcscInstruction::GeneralAuthenticate {
+                address,
+                key_type,
+                key_id,
+            },
+            0x00,
+            0x00,
+        )
+    }
+
+    /// Verify a PIN against the reference data selected by `reference` (sent in P2)
+    pub fn verify_pin(reference: u8, pin: &[u8]) -> Self {
+        Self::new(
+            PcscInstruction::Verify { data: pin.to_vec() },
+            0x00,
+            reference,
+        )
+    }
+
+    pub fn update_binary(address: u16, data: Vec<u8>) -> Self {
+        let [p1, p2] = address.to_be_bytes();
+        Self::new(PcscInstruction::UpdateBinary { data }, p1, p2)
+    }
+
+    /// ISO15693 Read Single Block, sent unaddressed with the request `flags` byte
+    pub fn iso15693_read_single_block(flags: u8, block: u8) -> Self {
+        Self::direct_transmit(vec![flags, 0x20, block])
+    }
+
+    /// ISO15693 Read Multiple Blocks of `count` blocks (1-256) from `first`
+    pub fn iso15693_read_multiple_blocks(flags: u8, first: u8, count: u16) -> Self {
+        // The frame carries the number of blocks minus one
+        let count = count.clamp(1, 256) - 1;
+        Self::direct_transmit(vec![flags, 0x23, first, count as u8])
+    }
+
+    /// ISO15693 Write Single Block; `data` must match the tag's block size
+    pub fn iso15693_write_single_block(flags: u8, block: u8, data: &[u8]) -> Self {
+        let mut frame = vec![flags, 0x21, block];
+        frame.extend_from_slice(data);
+        Self::direct_transmit(frame)
+    }
+
+    /// PC/SC 2.02 part 3 Manage Session (`FF C2 00 00`) carrying BER-TLV data objects, e.g.
+    /// `81 00` to start a transparent session
+    pub fn manage_session(objects: &[u8]) -> Self {
+        Self::new(
+            PcscInstruction::ManageSession {
+                data: objects.to_vec(),
+            },
+            0x00,
+            0x00,
+        )
+    }
+
+    /// PC/SC 2.02 part 3 Transparent Exchange (`FF C2 00 01`) of a raw frame to the card
+    /// inside a transparent session
+    pub fn transparent_exchange(frame: &[u8]) -> Self {
+        let mut data = vec![0x95, frame.len() as u8];
+        data.extend_from_slice(frame);
+        Self::new(PcscInstruction::ManageSession { data }, 0x00, 0x01)
+    }
+
+    /// PC/SC 2.02 part 3 Switch Protocol (`FF C2 00 02`) to `protocol` (e.g. 0x00 ISO14443A)
+    /// at `layer` (e.g. 0x04 for ISO14443-4)
+    pub fn switch_protocol(protocol: u8, layer: u8) -> Self {
+        Self::new(
+            PcscInstruction::ManageSession {
+                data: vec![0x8F, 0x02, protocol, layer],
+            },
+            0x00,
+            0x02,
+        )
+    }
+
+    /// Select an application by AID, asking for its FCI
+    pub fn select_aid(aid: &[u8]) -> Self {
+        Self::select_file(SelectMode::DfName, aid)
+    }
+
+    /// Select a file by identifier, name or path, asking for its FCI
+    pub fn select_file(mode: SelectMode, id: &[u8]) -> Self {
+        Self::new(
+            PcscInstruction::Select { data: id.to_vec() },
+            mode.p1(),
+            0x00,
+        )
+    }
+
+    fn direct_transmit(data: Vec<u8>) -> Self {
+        Self::new(PcscInstruction::DirectTransmit { data }, 0x00, 0x00)
+    }
+
+    pub fn ins(&self) -> &PcscInstruction {
+        &self.ins
+    }
+
+    pub fn p1(&self) -> u8 {
+        self.p1
+    }
+
+    pub fn p2(&self) -> u8 {
+        self.p2
+    }
+
+    /// Class byte: FF for reader pseudo-APDUs, 00 for interindustry card commands
+    pub fn cla(&self) -> u8 {
+        match self.ins {
+            PcscInstruction::Select { .. } => 0x00,
+            _ => 0xFF,
+        }
+    }
+
     pub fn ins_code(&self) -> u8 {
         match self.ins {
             PcscInstruction::GetData { .. } => 0xCA,
@@ -101,27 +541,138 @@ impl PcscCommand {
             PcscInstruction::ManageSession { .. } => 0xC2,
             PcscInstruction::ReadBinary { .. } => 0xB0,
             PcscInstruction::UpdateBinary { .. } => 0xD6,
+            PcscInstruction::DirectTransmit { .. } => 0x00,
+            PcscInstruction::Select { .. } => 0xA4,
+        }
+    }
+
+    /// Length of the encoded APDU, without encoding it
+    pub fn encoded_len(&self) -> usize {
+        match &self.ins {
+            PcscInstruction::ReadBinary { le } if *le > u8::MAX as u16 => 7,
+            PcscInstruction::GetData { .. } | PcscInstruction::ReadBinary { .. } => 5,
+            PcscInstruction::LoadKeys { data }
+            | PcscInstruction::Verify { data }
+            | PcscInstruction::ManageSession { data }
+            | PcscInstruction::UpdateBinary { data }
+            | PcscInstruction::DirectTransmit { data } => match data.len() {
+                0 => Self::MIN_LENGTH,
+                len => 5 + len,
+            },
+            PcscInstruction::GeneralAuthenticate { .. } => 10,
+            PcscInstruction::Select { data } if data.is_empty() => 5,
+            PcscInstruction::Select { data } => 6 + data.len(),
         }
     }
 
     pub fn expected_response_len(&self) -> usize {
         match &self.ins {
-            PcscInstruction::GetData { le } | PcscInstruction::ReadBinary { le } => {
-                if *le == 0 {
-                    PcscResponse::MAX_LENGTH
-                } else {
-                    *le as usize + 2
-                }
-            }
+            PcscInstruction::GetData { le } => response_len_for_le((*le).into()),
+            PcscInstruction::ReadBinary { le } => response_len_for_le(*le),
+            // Manage Session answers with C0/97 data objects ahead of the status words
+            PcscInstruction::DirectTransmit { .. }
+            | PcscInstruction::Select { .. }
+            | PcscInstruction::ManageSession { .. } => PcscResponse::MAX_LENGTH,
             PcscInstruction::LoadKeys { .. }
             | PcscInstruction::GeneralAuthenticate { .. }
             | PcscInstruction::Verify { .. }
-            | PcscInstruction::ManageSession { .. }
             | PcscInstruction::UpdateBinary { .. } => 2,
         }
     }
 }
 
+// Response length for an Le, status words included; Le 0 asks for 256 bytes, as much as a short
+// response holds
+fn response_len_for_le(le: u16) -> usize {
+    if le == 0 {
+        PcscResponse::MAX_LENGTH
+    } else {
+        le as usize + 2
+    }
+}
+
+/// ISO7816-3 command APDU structure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApduCase {
+    /// Header only
+    Case1,
+    /// Le only
+    Case2Short,
+    /// Lc and data
+    Case3Short,
+    /// Lc, data and Le
+    Case4Short,
+    Case2Extended,
+    Case3Extended,
+    Case4Extended,
+}
+
+/// Classify a command APDU by which of Lc, data and Le follow its header
+pub fn apdu_case(bytes: &[u8]) -> Result<ApduCase, PcscCodecError> {
+    let body = bytes
+        .get(PcscCommand::MIN_LENGTH..)
+        .ok_or(PcscCodecError::TooShort)?;
+    match body {
+        [] => Ok(ApduCase::Case1),
+        [_] => Ok(ApduCase::Case2Short),
+        // Extended length: a zero byte, then two byte Lc or Le
+        [0x00, _, _] => Ok(ApduCase::Case2Extended),
+        // ISO7816-4 encodes an absent data field by omitting Lc, never as Lc 0
+        [0x00, 0x00, 0x00, ..] | [0x00, _] => Err(PcscCodecError::ZeroLc),
+        [0x00, lc_msb, lc_lsb, rest @ ..] => {
+            let lc = u16::from_be_bytes([*lc_msb, *lc_lsb]) as usize;
+            match rest.len().checked_sub(lc) {
+                Some(0) => Ok(ApduCase::Case3Extended),
+                Some(2) => Ok(ApduCase::Case4Extended),
+                Some(_) => Err(PcscCodecError::TooLong),
+                None => Err(PcscCodecError::TooShort),
+            }
+        }
+        [lc, rest @ ..] => match rest.len().checked_sub(*lc as usize) {
+            Some(0) => Ok(ApduCase::Case3Short),
+            Some(1) => Ok(ApduCase::Case4Short),
+            Some(_) => Err(PcscCodecError::TooLong),
+            None => Err(PcscCodecError::TooShort),
+        },
+    }
+}
+
+// Data field of a command whose body is an optional Lc followed by data
+fn command_data(value: &[u8]) -> Result<Vec<u8>, PcscCodecError> {
+    let Some(&lc) = value.get(4) else {
+        // Case 1: header only
+        return Ok(vec![]);
+    };
+    // Without data there is no Lc byte, so a 0 here is malformed
+    if lc == 0 {
+        return Err(PcscCodecError::ZeroLc);
+    }
+    let eod = 5 + (lc as usize);
+    if value.len() < eod {
+        return Err(PcscCodecError::TooShort);
+    }
+    if value.len() > eod {
+        return Err(PcscCodecError::TooLong);
+    }
+    Ok(value[5..eod].to_vec())
+}
+
+// Case 3 or 4 SELECT: Lc, data, then an optional Le
+fn select_command(value: &[u8]) -> Result<PcscCommand, PcscCodecError> {
+    let lc = *value.get(4).ok_or(PcscCodecError::TooShort)? as usize;
+    let data = value.get(5..5 + lc).ok_or(PcscCodecError::TooShort)?;
+    if value.len() > 6 + lc {
+        return Err(PcscCodecError::TooLong);
+    }
+    Ok(PcscCommand::new(
+        PcscInstruction::Select {
+            data: data.to_vec(),
+        },
+        value[2],
+        value[3],
+    ))
+}
+
 impl TryFrom<&[u8]> for PcscCommand {
     type Error = PcscCodecError;
 
@@ -132,29 +683,26 @@ impl TryFrom<&[u8]> for PcscCommand {
         if value.len() > Self::MAX_LENGTH {
             return Err(PcscCodecError::TooLong);
         }
+        if value[0] == 0x00 && value[1] == 0xA4 {
+            return select_command(value);
+        }
         if value[0] != 0xFF {
             return Err(PcscCodecError::WrongClass);
         }
         let p1 = value[2];
         let p2 = value[3];
         let ins = match value[1] {
-            0xCA => PcscInstruction::GetData { le: value[4] },
-            0x82 => {
-                let lc = value[4];
-                let eod = 5 + (lc as usize);
-                if value.len() < eod {
-                    return Err(PcscCodecError::TooShort);
-                }
-                PcscInstruction::LoadKeys {
-                    data: value[5..eod].to_vec(),
-                }
-            }
+            0xCA => PcscInstruction::GetData {
+                le: *value.get(4).ok_or(PcscCodecError::TooShort)?,
+            },
+            0x82 => PcscInstruction::LoadKeys {
+                data: command_data(value)?,
+            },
             0x86 => {
-                let lc = value[4];
-                if lc < 5 || value.len() < 10 {
+                if value.len() < 10 || value[4] < 5 {
                     return Err(PcscCodecError::TooShort);
                 }
-                if lc > 5 {
+                if value[4] > 5 {
                     return Err(PcscCodecError::TooLong);
                 }
                 if value[5] != 0x01 {
@@ -166,37 +714,26 @@ impl TryFrom<&[u8]> for PcscCommand {
                     key_id: value[9],
                 }
             }
-            0x20 => {
-                let lc = value[4];
-                let eod = 5 + (lc as usize);
-                if value.len() < eod {
-                    return Err(PcscCodecError::TooShort);
-                }
-                PcscInstruction::Verify {
-                    data: value[5..eod].to_vec(),
-                }
-            }
-            0xC2 => {
-                let lc = value[4];
-                let eod = 5 + (lc as usize);
-                if value.len() < eod {
-                    return Err(PcscCodecError::TooShort);
-                }
-                PcscInstruction::ManageSession {
-                    data: value[5..eod].to_vec(),
-                }
-            }
-            0xB0 => PcscInstruction::ReadBinary { le: value[4] },
-            0xD6 => {
-                let lc = value[4];
-                let eod = 5 + (lc as usize);
-                if value.len() < eod {
-                    return Err(PcscCodecError::TooShort);
-                }
-                PcscInstruction::UpdateBinary {
-                    data: value[5..eod].to_vec(),
-                }
-            }
+            0x20 => PcscInstruction::Verify {
+                data: command_data(value)?,
+            },
+            0xC2 => PcscInstruction::ManageSession {
+                data: command_data(value)?,
+            },
+            0xB0 => match value[4..] {
+                [le] => PcscInstruction::ReadBinary { le: le.into() },
+                [0x00, msb, lsb] => PcscInstruction::ReadBinary {
+                    le: u16::from_be_bytes([msb, lsb]),
+                },
+                [] | [_, _] => return Err(PcscCodecError::TooShort),
+                _ => return Err(PcscCodecError::TooLong),
+            },
+            0xD6 => PcscInstruction::UpdateBinary {
+                data: command_data(value)?,
+            },
+            0x00 => PcscInstruction::DirectTransmit {
+                data: command_data(value)?,
+            },
             _ => return Err(PcscCodecError::UnknownIns),
         };
         Ok(Self { ins, p1, p2 })
@@ -209,18 +746,41 @@ impl TryFrom<PcscCommand> for Vec<u8> {
     fn try_from(value: PcscCommand) -> Result<Self, Self::Error> {
         let ins = value.ins_code();
         Ok(match value.ins {
-            PcscInstruction::GetData { le } | PcscInstruction::ReadBinary { le } => {
-                vec![0xFF, ins, value.p1, value.p2, le]
+            PcscInstruction::Select { data } => {
+                if data.len() > u8::MAX as usize {
+                    return Err(PcscCodecError::TooLong);
+                }
+                // Case 2: no identifier means no Lc, only Le
+                if data.is_empty() {
+                    return Ok(vec![0x00, ins, value.p1, value.p2, 0x00]);
+                }
+                let mut output = vec![0x00, ins, value.p1, value.p2, data.len() as u8];
+                output.extend(data);
+                output.push(0x00); // Le: the whole FCI
+                output
             }
+            PcscInstruction::GetData { le } => vec![0xFF, ins, value.p1, value.p2, le],
+            PcscInstruction::ReadBinary { le } => match u8::try_from(le) {
+                Ok(le) => vec![0xFF, ins, value.p1, value.p2, le],
+                Err(_) => {
+                    let [le_msb, le_lsb] = le.to_be_bytes();
+                    vec![0xFF, ins, value.p1, value.p2, 0x00, le_msb, le_lsb]
+                }
+            },
             PcscInstruction::LoadKeys { data }
             | PcscInstruction::Verify { data }
             | PcscInstruction::ManageSession { data }
-            | PcscInstruction::UpdateBinary { data } => {
+            | PcscInstruction::UpdateBinary { data }
+            | PcscInstruction::DirectTransmit { data } => {
                 let lc = data.len();
                 if lc > u8::MAX as usize {
                     return Err(PcscCodecError::TooLong);
                 }
-                let mut output = vec![0xFF, 0x82, value.p1, value.p2, lc as u8];
+                // Case 1: no data means no Lc byte either
+                if lc == 0 {
+                    return Ok(vec![0xFF, ins, value.p1, value.p2]);
+                }
+                let mut output = vec![0xFF, ins, value.p1, value.p2, lc as u8];
                 output.extend(data);
                 output
             }
@@ -247,11 +807,30 @@ impl TryFrom<PcscCommand> for Vec<u8> {
     }
 }
 
+// Status words whose meaning does not depend on the command, the one table behind both
+// extra_info and all_known
+const GENERAL_INFO: &[(u8, u8, PcscErrorCodeInfo)] = &[
+    (0x62, 0x81, PcscErrorCodeInfo::ResponseCorrupted),
+    (0x62, 0x82, PcscErrorCodeInfo::UnexpectedEndOfData),
+    (0x64, 0x01, PcscErrorCodeInfo::ImmediateResponseRequired),
+    (0x66, 0x69, PcscErrorCodeInfo::IncorrectPadding),
+    (0x6A, 0x81, PcscErrorCodeInfo::FunctionNotSupported),
+    (0x6A, 0x82, PcscErrorCodeInfo::FileNotFound),
+    (0x6A, 0x88, PcscErrorCodeInfo::ReferenceDataNotFound),
+];
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PcscStatusWords {
+    BytesRemaining(u8),
     Warning(u8),
     AllowedRetries(u8),
+    /// 63 Cx: verification failed, x tries left
+    RetriesRemaining(u8),
+    /// 64 xx: execution error, non-volatile memory unchanged
+    ExecutionError(u8),
     MemoryFailure(u8),
+    /// 66 xx: security-related issue, e.g. secure messaging failures
+    SecurityError(u8),
     WrongLength,
     WrongClassByte,
     CommandImpossible(u8),
@@ -259,9 +838,13 @@ pub enum PcscStatusWords {
     WrongParameter,
     WrongLengthLe(u8),
     Success,
-    Unknown { sw1: u8, sw2: u8 },
+    Unknown {
+        sw1: u8,
+        sw2: u8,
+    },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PcscErrorCodeInfo {
     ResponseCorrupted,
     UnexpectedEndOfData,
@@ -283,15 +866,169 @@ pub enum PcscErrorCodeInfo {
     FunctionNotSupported,
     FileNotFound,
     ReferenceDataNotFound,
+    ImmediateResponseRequired,
+    IncorrectPadding,
+}
+
+impl PcscErrorCodeInfo {
+    pub fn description(&self) -> &'static str {
+        match self {
+            PcscErrorCodeInfo::ResponseCorrupted => "Part of the returned data may be corrupted",
+            PcscErrorCodeInfo::UnexpectedEndOfData => "End of data reached before Le bytes",
+            PcscErrorCodeInfo::AddressDoesNotExit => "Address does not exist",
+            PcscErrorCodeInfo::WritingFailed => "Writing to memory failed",
+            PcscErrorCodeInfo::CommandIncompatible => {
+                "Command incompatible with the file structure"
+            }
+            PcscErrorCodeInfo::CardKeyNotSupported => "Card key not supported",
+            PcscErrorCodeInfo::ReaderKeyNotSupported => "Reader key not supported",
+            PcscErrorCodeInfo::PlainTransmissionNotSupported => "Plain transmission not supported",
+            PcscErrorCodeInfo::SecuredTransmissionNotSupported => {
+                "Secured transmission not supported"
+            }
+            PcscErrorCodeInfo::VolatileMemoryUnavailable => "Volatile memory not available",
+            PcscErrorCodeInfo::NonVolatileMemoryUnavailable => "Non-volatile memory not available",
+            PcscErrorCodeInfo::KeyNumberNotValid => "Key number not valid",
+            PcscErrorCodeInfo::KeyLengthIncorrect => "Key length incorrect",
+            PcscErrorCodeInfo::SecurityStatusUnsatisfied => "Security status not satisfied",
+            PcscErrorCodeInfo::ReferenceKeyUnusable => "Reference key not usable",
+            PcscErrorCodeInfo::UnknownKeyType => "Key type not known",
+            PcscErrorCodeInfo::CommandNotAllowed => "Command not allowed",
+            PcscErrorCodeInfo::FunctionNotSupported => "Function not supported",
+            PcscErrorCodeInfo::FileNotFound => "File or application not found",
+            PcscErrorCodeInfo::ReferenceDataNotFound => "Referenced data not found",
+            PcscErrorCodeInfo::ImmediateResponseRequired => {
+                "Immediate response required by the card"
+            }
+            PcscErrorCodeInfo::IncorrectPadding => "Incorrect secure messaging padding",
+        }
+    }
 }
 
 impl PcscStatusWords {
+    /// Decode SW1 SW2
+    pub fn from_bytes(sw1: u8, sw2: u8) -> Self {
+        match sw1 {
+            0x61 => PcscStatusWords::BytesRemaining(sw2),
+            0x62 => PcscStatusWords::Warning(sw2),
+            0x63 => match sw2 {
+                0xC0..=0xCF => PcscStatusWords::RetriesRemaining(sw2 & 0x0F),
+                _ => PcscStatusWords::AllowedRetries(sw2),
+            },
+            0x64 => PcscStatusWords::ExecutionError(sw2),
+            0x65 => PcscStatusWords::MemoryFailure(sw2),
+            0x66 => PcscStatusWords::SecurityError(sw2),
+            0x67 => PcscStatusWords::WrongLength,
+            0x68 => PcscStatusWords::WrongClassByte,
+            0x69 => PcscStatusWords::CommandImpossible(sw2),
+            0x6A => PcscStatusWords::CommandError(sw2),
+            0x6B => PcscStatusWords::WrongParameter,
+            0x6C => PcscStatusWords::WrongLengthLe(sw2),
+            0x90 => PcscStatusWords::Success,
+            _ => PcscStatusWords::Unknown { sw1, sw2 },
+        }
+    }
+
+    /// Encode back to SW1 SW2
+    pub fn to_bytes(&self) -> [u8; 2] {
+        match *self {
+            PcscStatusWords::BytesRemaining(sw2) => [0x61, sw2],
+            PcscStatusWords::Warning(sw2) => [0x62, sw2],
+            PcscStatusWords::AllowedRetries(sw2) => [0x63, sw2],
+            PcscStatusWords::RetriesRemaining(retries) => [0x63, 0xC0 | (retries & 0x0F)],
+            PcscStatusWords::ExecutionError(sw2) => [0x64, sw2],
+            PcscStatusWords::MemoryFailure(sw2) => [0x65, sw2],
+            PcscStatusWords::SecurityError(sw2) => [0x66, sw2],
+            PcscStatusWords::WrongLength => [0x67, 0x00],
+            PcscStatusWords::WrongClassByte => [0x68, 0x00],
+            PcscStatusWords::CommandImpossible(sw2) => [0x69, sw2],
+            PcscStatusWords::CommandError(sw2) => [0x6A, sw2],
+            PcscStatusWords::WrongParameter => [0x6B, 0x00],
+            PcscStatusWords::WrongLengthLe(sw2) => [0x6C, sw2],
+            PcscStatusWords::Success => [0x90, 0x00],
+            PcscStatusWords::Unknown { sw1, sw2 } => [sw1, sw2],
+        }
+    }
+
+    /// Every recognized status word as (SW1, SW2, description), e.g. for a lookup table: the
+    /// first status word decoding to each variant, described by [`PcscStatusWords::description`],
+    /// then those with a meaning of their own whatever the command. Meanings that depend on the
+    /// command are left to [`PcscStatusWords::extra_info`].
+    pub fn all_known() -> &'static [(u8, u8, &'static str)] {
+        static KNOWN: OnceLock<Vec<(u8, u8, &'static str)>> = OnceLock::new();
+        KNOWN.get_or_init(|| {
+            let mut known: Vec<(u8, u8, &'static str)> = Vec::new();
+            let mut seen = Vec::new();
+            for [sw1, sw2] in (0..=u16::MAX).map(u16::to_be_bytes) {
+                let sw = PcscStatusWords::from_bytes(sw1, sw2);
+                let variant = mem::discriminant(&sw);
+                if !matches!(sw, PcscStatusWords::Unknown { .. }) && !seen.contains(&variant) {
+                    seen.push(variant);
+                    known.push((sw1, sw2, sw.description()));
+                }
+            }
+            known.extend(
+                GENERAL_INFO
+                    .iter()
+                    .map(|&(sw1, sw2, info)| (sw1, sw2, info.description())),
+            );
+            known.sort_by_key(|&(sw1, sw2, _)| (sw1, sw2));
+            known
+        })
+    }
+
+    /// What the status words mean regardless of SW2
+    pub fn description(&self) -> &'static str {
+        match self {
+            PcscStatusWords::BytesRemaining(_) => "Response bytes still available, SW2 counts them",
+            PcscStatusWords::Warning(_) => "Warning, non-volatile memory unchanged",
+            PcscStatusWords::AllowedRetries(_) => "Warning, non-volatile memory changed",
+            PcscStatusWords::RetriesRemaining(_) => {
+                "Verification failed, low nibble of SW2 counts the tries left"
+            }
+            PcscStatusWords::ExecutionError(_) => "Execution error, non-volatile memory unchanged",
+            PcscStatusWords::MemoryFailure(_) => "Execution error, non-volatile memory changed",
+            PcscStatusWords::SecurityError(_) => "Security-related issue",
+            PcscStatusWords::WrongLength => "Wrong length",
+            PcscStatusWords::WrongClassByte => "Function in CLA not supported",
+            PcscStatusWords::CommandImpossible(_) => "Command not allowed",
+            PcscStatusWords::CommandError(_) => "Wrong parameters P1-P2",
+            PcscStatusWords::WrongParameter => "Wrong parameters P1-P2, offset outside the file",
+            PcscStatusWords::WrongLengthLe(_) => "Wrong Le, SW2 gives the exact length",
+            PcscStatusWords::Success => "Success",
+            PcscStatusWords::Unknown { .. } => "Unknown status",
+        }
+    }
+
+    /// Tries left before the verified reference data locks (63 Cx)
+    pub fn retries_remaining(&self) -> Option<u8> {
+        match self {
+            PcscStatusWords::RetriesRemaining(retries) => Some(*retries),
+            _ => None,
+        }
+    }
+
+    /// `Ok` on success, otherwise a [`PcscCodecError::StatusError`] decoded against `ins`
+    pub fn into_result(self, ins: u8) -> Result<(), PcscCodecError> {
+        match self {
+            PcscStatusWords::Success => Ok(()),
+            sw => Err(PcscCodecError::status(sw, ins)),
+        }
+    }
+
+    /// Meaning of SW2 for the command with instruction `ins`, where this crate knows one
     pub fn extra_info(&self, ins: u8) -> Option<PcscErrorCodeInfo> {
+        let bytes = self.to_bytes();
+        if let Some(&(_, _, info)) = GENERAL_INFO
+            .iter()
+            .find(|&&(sw1, sw2, _)| [sw1, sw2] == bytes)
+        {
+            return Some(info);
+        }
         match self {
-            // 0x62
-            PcscStatusWords::Warning(sw2) => match sw2 {
-                0x81 => Some(PcscErrorCodeInfo::ResponseCorrupted),
-                0x82 => Some(PcscErrorCodeInfo::UnexpectedEndOfData),
+            // 0x64
+            PcscStatusWords::ExecutionError(sw2) => match (ins, sw2) {
+                (0xD6, 0x00) => Some(PcscErrorCodeInfo::WritingFailed),
                 _ => None,
             },
             // 0x65
@@ -333,14 +1070,12 @@ impl PcscStatusWords {
                 (0xD6, 0x86) => Some(PcscErrorCodeInfo::CommandNotAllowed),
                 _ => None,
             },
-            // 0x6A
-            PcscStatusWords::CommandError(sw2) => match sw2 {
-                0x81 => Some(PcscErrorCodeInfo::FunctionNotSupported),
-                0x82 => Some(PcscErrorCodeInfo::FileNotFound),
-                0x88 => Some(PcscErrorCodeInfo::ReferenceDataNotFound),
-                _ => None,
-            },
-            PcscStatusWords::AllowedRetries(_)
+            PcscStatusWords::Warning(_)
+            | PcscStatusWords::SecurityError(_)
+            | PcscStatusWords::CommandError(_)
+            | PcscStatusWords::BytesRemaining(_)
+            | PcscStatusWords::AllowedRetries(_)
+            | PcscStatusWords::RetriesRemaining(_)
             | PcscStatusWords::WrongLength
             | PcscStatusWords::WrongClassByte
             | PcscStatusWords::WrongParameter
@@ -359,54 +1094,64 @@ pub struct PcscResponse {
 
 impl PcscResponse {
     pub const MIN_LENGTH: usize = 2;
-    pub const MAX_LENGTH: usize = 2 + u8::MAX as usize;
+    /// Largest short response: the 256 bytes a Le of 0 asks for, then SW1 SW2
+    pub const MAX_LENGTH: usize = 2 + 256;
+
+    /// Build a response directly, e.g. for a proxy or a test double
+    pub fn new(data: Vec<u8>, sw: PcscStatusWords) -> Self {
+        Self { data, sw }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn sw(&self) -> PcscStatusWords {
+        self.sw
+    }
+
+    /// Iterate over the BER-TLV data objects in the response data
+    pub fn tlv(&self) -> TlvIter<'_> {
+        TlvIter::new(&self.data)
+    }
+
+    /// Take ownership of the response data and status words
+    pub fn into_parts(self) -> (Vec<u8>, PcscStatusWords) {
+        (self.data, self.sw)
+    }
+
+    /// The response data on success, otherwise the status error decoded against `ins`
+    pub fn into_data(self, ins: u8) -> Result<Vec<u8>, PcscCodecError> {
+        self.sw.into_result(ins)?;
+        Ok(self.data)
+    }
+
+    /// Append the data of a follow-up response, taking on its status words
+    pub(crate) fn extend(&mut self, next: PcscResponse) {
+        self.data.extend(next.data);
+        self.sw = next.sw;
+    }
 }
 
 impl TryFrom<&[u8]> for PcscResponse {
     type Error = PcscCodecError;
 
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let (data, eod) = match value.len() {
-            0 | 1 => return Err(PcscCodecError::TooShort),
-            Self::MIN_LENGTH => (vec![], 0),
-            len => (value[0..len - 2].to_vec(), len - 2),
-        };
-        let sw = match value[eod] {
-            0x62 => PcscStatusWords::Warning(value[eod + 1]),
-            0x63 => PcscStatusWords::AllowedRetries(value[eod + 1]),
-            0x65 => PcscStatusWords::MemoryFailure(value[eod + 1]),
-            0x67 => PcscStatusWords::WrongLength,
-            0x68 => PcscStatusWords::WrongClassByte,
-            0x69 => PcscStatusWords::CommandImpossible(value[eod + 1]),
-            0x6A => PcscStatusWords::CommandError(value[eod + 1]),
-            0x6B => PcscStatusWords::WrongParameter,
-            0x6C => PcscStatusWords::WrongLengthLe(value[eod + 1]),
-            0x90 => PcscStatusWords::Success,
-            sw1 => PcscStatusWords::Unknown {
-                sw1,
-                sw2: value[eod + 1],
-            },
+        // Split off the status words up front so no branch below can index out of bounds
+        let Some((&[sw1, sw2], data)) = value
+            .split_last_chunk::<2>()
+            .map(|(data, sw)| (sw, data.to_vec()))
+        else {
+            return Err(PcscCodecError::TooShort);
         };
-        Ok(Self { data, sw })
+        Ok(Self::new(data, PcscStatusWords::from_bytes(sw1, sw2)))
     }
 }
 
 impl From<PcscResponse> for Vec<u8> {
     fn from(value: PcscResponse) -> Self {
         let mut output = value.data;
-        output.extend(match value.sw {
-            PcscStatusWords::Warning(sw2) => [0x62, sw2],
-            PcscStatusWords::AllowedRetries(sw2) => [0x63, sw2],
-            PcscStatusWords::MemoryFailure(sw2) => [0x65, sw2],
-            PcscStatusWords::WrongLength => [0x67, 0x00],
-            PcscStatusWords::WrongClassByte => [0x68, 0x00],
-            PcscStatusWords::CommandImpossible(sw2) => [0x69, sw2],
-            PcscStatusWords::CommandError(sw2) => [0x6A, sw2],
-            PcscStatusWords::WrongParameter => [0x6B, 0x00],
-            PcscStatusWords::WrongLengthLe(sw2) => [0x6C, sw2],
-            PcscStatusWords::Success => [0x90, 0x00],
-            PcscStatusWords::Unknown { sw1, sw2 } => [sw1, sw2],
-        });
+        output.extend(value.sw.to_bytes());
         output
     }
 }
@@ -415,6 +1160,85 @@ impl From<PcscResponse> for Vec<u8> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn known_status_words_round_trip() {
+        for &(sw1, sw2, description) in PcscStatusWords::all_known() {
+            let sw = PcscStatusWords::from_bytes(sw1, sw2);
+            assert!(
+                !matches!(sw, PcscStatusWords::Unknown { .. }),
+                "{sw1:02X} {sw2:02X} {description}"
+            );
+            assert_eq!(sw.to_bytes(), [sw1, sw2], "{description}");
+            // The description is what the decoder makes of the status words
+            let expected = sw
+                .extra_info(0x00)
+                .map_or(sw.description(), |info| info.description());
+            assert_eq!(description, expected, "{sw1:02X} {sw2:02X}");
+        }
+        let listed = |sw1, sw2| {
+            PcscStatusWords::all_known()
+                .iter()
+                .any(|known| (known.0, known.1) == (sw1, sw2))
+        };
+        for sw1 in 0..=u8::MAX {
+            if !matches!(
+                PcscStatusWords::from_bytes(sw1, 0x00),
+                PcscStatusWords::Unknown { .. }
+            ) {
+                assert!(listed(sw1, 0x00), "{sw1:02X} missing");
+            }
+        }
+        assert!(listed(0x63, 0xC0));
+        assert!(listed(0x6A, 0x82));
+        // Meanings that depend on the command are not listed
+        assert!(!listed(0x65, 0x81));
+        assert!(!listed(0x69, 0x83));
+    }
+
+    #[test]
+    fn error_categories() {
+        let cases = [
+            (
+                PcscCodecError::Pcsc(PcscError::NoSmartcard),
+                ErrorCategory::Transport,
+            ),
+            (
+                PcscCodecError::ResponseTooShort { len: 1, min_len: 2 },
+                ErrorCategory::Transport,
+            ),
+            (PcscCodecError::TooShort, ErrorCategory::Encoding),
+            (PcscCodecError::TooLong, ErrorCategory::Encoding),
+            (PcscCodecError::ZeroLc, ErrorCategory::Encoding),
+            (PcscCodecError::WrongClass, ErrorCategory::Encoding),
+            (PcscCodecError::UnknownIns, ErrorCategory::Encoding),
+            (
+                PcscCodecError::UnknownGeneralAuthenticateVersion,
+                ErrorCategory::Encoding,
+            ),
+            (PcscCodecError::AddressOutOfRange, ErrorCategory::Encoding),
+            (
+                PcscCodecError::UnsupportedReader(ReaderModel::Unknown),
+                ErrorCategory::Encoding,
+            ),
+            (
+                PcscCodecError::status(PcscStatusWords::WrongLength, 0xB0),
+                ErrorCategory::Card,
+            ),
+            (PcscCodecError::UnsupportedCard, ErrorCategory::Card),
+            (PcscCodecError::AuthenticationFailed, ErrorCategory::Card),
+            (
+                PcscCodecError::Block {
+                    block: 4,
+                    source: Box::new(PcscCodecError::Pcsc(PcscError::Timeout)),
+                },
+                ErrorCategory::Transport,
+            ),
+        ];
+        for (error, category) in cases {
+            assert_eq!(error.category(), category, "{error:?}");
+        }
+    }
+
     fn get_data() -> [u8; 5] {
         [0xff, 0xca, 0x00, 0x00, 0x00]
     }
@@ -470,6 +1294,220 @@ mod tests {
         assert_eq!(bytes, expected);
     }
 
+    #[test]
+    fn get_data_uid() {
+        let bytes: Vec<u8> = PcscCommand::get_data(GetDataType::Uid).try_into().unwrap();
+        assert_eq!(bytes, vec![0xFF, 0xCA, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn get_data_historical_bytes() {
+        let bytes: Vec<u8> = PcscCommand::get_data(GetDataType::HistoricalBytes)
+            .try_into()
+            .unwrap();
+        assert_eq!(bytes, vec![0xFF, 0xCA, 0x01, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn get_data_ats() {
+        let bytes: Vec<u8> = PcscCommand::get_data(GetDataType::Ats).try_into().unwrap();
+        assert_eq!(bytes, vec![0xFF, 0xCA, 0x01, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn header_only_from_u8() {
+        let expected = PcscCommand {
+            ins: PcscInstruction::Verify { data: vec![] },
+            p1: 0x00,
+            p2: 0x80,
+        };
+        let command = PcscCommand::try_from(&[0xFF, 0x20, 0x00, 0x80][..]).unwrap();
+        assert_eq!(command, expected);
+        let bytes: Vec<u8> = command.try_into().unwrap();
+        assert_eq!(bytes, vec![0xFF, 0x20, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn header_only_requires_le() {
+        assert!(matches!(
+            PcscCommand::try_from(&[0xFF, 0xB0, 0x00, 0x04][..]),
+            Err(PcscCodecError::TooShort)
+        ));
+    }
+
+    #[test]
+    fn lc_longer_than_data() {
+        for ins in [0x82, 0x20, 0xD6] {
+            assert!(matches!(
+                PcscCommand::try_from(&[0xFF, ins, 0x00, 0x00, 0x06, 0x01, 0x02][..]),
+                Err(PcscCodecError::TooShort)
+            ));
+        }
+    }
+
+    #[test]
+    fn lc_shorter_than_data() {
+        for ins in [0x82, 0x20, 0xD6] {
+            assert!(matches!(
+                PcscCommand::try_from(&[0xFF, ins, 0x00, 0x00, 0x01, 0x01, 0x02][..]),
+                Err(PcscCodecError::TooLong)
+            ));
+        }
+    }
+
+    #[test]
+    fn data_command_zero_lc() {
+        for ins in [0x82, 0x20, 0xC2, 0xD6, 0x00] {
+            assert!(matches!(
+                PcscCommand::try_from(&[0xFF, ins, 0x00, 0x00, 0x00][..]),
+                Err(PcscCodecError::ZeroLc)
+            ));
+        }
+    }
+
+    #[test]
+    fn read_binary_layout() {
+        let bytes: Vec<u8> = PcscCommand::read_binary(0x0104, 0x10).try_into().unwrap();
+        assert_eq!(bytes, vec![0xFF, 0xB0, 0x01, 0x04, 0x10]);
+    }
+
+    #[test]
+    fn short_le_zero_sized_for_256_bytes() {
+        assert_eq!(PcscCommand::read_binary(0, 0).expected_response_len(), 258);
+        assert_eq!(
+            PcscCommand::get_data(GetDataType::Uid).expected_response_len(),
+            258
+        );
+    }
+
+    #[test]
+    fn read_binary_extended_le() {
+        let command = PcscCommand::read_binary_extended(0x0000, 512);
+        assert_eq!(command.expected_response_len(), 514);
+        assert_eq!(command.encoded_len(), 7);
+        let bytes: Vec<u8> = command.clone().try_into().unwrap();
+        assert_eq!(bytes, vec![0xFF, 0xB0, 0x00, 0x00, 0x00, 0x02, 0x00]);
+        assert_eq!(PcscCommand::try_from(&bytes[..]).unwrap(), command);
+
+        // Up to 255 bytes still goes out as a short Le
+        let bytes: Vec<u8> = PcscCommand::read_binary_extended(0x0004, 0x10)
+            .try_into()
+            .unwrap();
+        assert_eq!(bytes, vec![0xFF, 0xB0, 0x00, 0x04, 0x10]);
+    }
+
+    #[test]
+    fn read_binary_sfi_p1() {
+        let bytes: Vec<u8> = PcscCommand::read_binary_sfi(1, 0x00, 0x00)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(bytes, vec![0xFF, 0xB0, 0x81, 0x00, 0x00]);
+        let command = PcscCommand::read_binary_sfi(30, 0x08, 0x10).unwrap();
+        assert_eq!((command.p1(), command.p2()), (0x9E, 0x08));
+        for sfi in [0, 31, 0x80] {
+            assert!(matches!(
+                PcscCommand::read_binary_sfi(sfi, 0, 0),
+                Err(PcscCodecError::AddressOutOfRange)
+            ));
+        }
+    }
+
+    #[test]
+    fn data_commands_keep_their_ins() {
+        let data = vec![0x01, 0x02];
+        let instructions = [
+            (PcscInstruction::LoadKeys { data: data.clone() }, 0x82),
+            (PcscInstruction::Verify { data: data.clone() }, 0x20),
+            (PcscInstruction::ManageSession { data: data.clone() }, 0xC2),
+            (PcscInstruction::UpdateBinary { data: data.clone() }, 0xD6),
+            (PcscInstruction::DirectTransmit { data: data.clone() }, 0x00),
+        ];
+        for (ins, code) in instructions {
+            let command = PcscCommand::new(ins, 0x00, 0x04);
+            let bytes: Vec<u8> = command.clone().try_into().unwrap();
+            assert_eq!(bytes, [0xFF, code, 0x00, 0x04, 0x02, 0x01, 0x02]);
+            assert_eq!(PcscCommand::try_from(&bytes[..]).unwrap(), command);
+        }
+    }
+
+    #[test]
+    fn update_binary_layout() {
+        let bytes: Vec<u8> = PcscCommand::update_binary(0x0004, vec![0xDE, 0xAD, 0xBE, 0xEF])
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            bytes,
+            vec![0xFF, 0xD6, 0x00, 0x04, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]
+        );
+    }
+
+    #[test]
+    fn clone_load_keys() {
+        let command = PcscCommand::new(
+            PcscInstruction::LoadKeys {
+                data: vec![0xFF; 6],
+            },
+            0x00,
+            0x01,
+        );
+        assert_eq!(command.clone(), command);
+    }
+
+    #[test]
+    fn verify_pin_to_u8() {
+        let bytes: Vec<u8> = PcscCommand::verify_pin(0x81, &[0x31, 0x32, 0x33, 0x34])
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            bytes,
+            vec![0xFF, 0x20, 0x00, 0x81, 0x04, 0x31, 0x32, 0x33, 0x34]
+        );
+    }
+
+    #[test]
+    fn verify_pin_from_u8() {
+        let bytes = [0xFF, 0x20, 0x00, 0x81, 0x04, 0x31, 0x32, 0x33, 0x34];
+        let command = PcscCommand::try_from(&bytes[..]).unwrap();
+        assert_eq!(command, PcscCommand::verify_pin(0x81, b"1234"));
+        assert_eq!(command.p2(), 0x81);
+        assert_eq!(
+            command.ins(),
+            &PcscInstruction::Verify {
+                data: b"1234".to_vec()
+            }
+        );
+    }
+
+    #[test]
+    fn retries_remaining_status() {
+        let response = PcscResponse::try_from(&[0x63, 0xC2][..]).unwrap();
+        assert_eq!(response.sw(), PcscStatusWords::RetriesRemaining(2));
+        assert_eq!(Vec::from(response), vec![0x63, 0xC2]);
+        let response = PcscResponse::try_from(&[0x63, 0x00][..]).unwrap();
+        assert_eq!(response.sw(), PcscStatusWords::AllowedRetries(0));
+    }
+
+    #[test]
+    fn retries_remaining() {
+        let sw = PcscResponse::try_from(&[0x63, 0xC3][..]).unwrap().sw();
+        assert_eq!(sw.retries_remaining(), Some(3));
+        let sw = PcscResponse::try_from(&[0x63, 0x00][..]).unwrap().sw();
+        assert_eq!(sw.retries_remaining(), None);
+        assert_eq!(PcscStatusWords::Success.retries_remaining(), None);
+    }
+
+    #[test]
+    fn general_authenticate_layout() {
+        let bytes: Vec<u8> = PcscCommand::general_authenticate(0x0080, KeyType::MifareB, 0x01)
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            bytes,
+            vec![0xFF, 0x86, 0x00, 0x00, 0x05, 0x01, 0x00, 0x80, 0x61, 0x01]
+        );
+    }
+
     #[test]
     fn load_keys_from_u8() {
         let expected = PcscCommand {
@@ -482,5 +1520,405 @@ mod tests {
         let load_keys = load_authentication_keys(1, [0, 1, 2, 3, 4, 5]);
         let command = PcscCommand::try_from(&load_keys[..]).unwrap();
         assert_eq!(command, expected);
+        assert_eq!(PcscCommand::load_key(1, [0, 1, 2, 3, 4, 5]), expected);
+    }
+
+    #[test]
+    fn load_key_options_p1() {
+        let cases = [
+            (KeyStructure::CardKey, false, 0x00),
+            (KeyStructure::CardKey, true, 0x20),
+            (KeyStructure::ReaderKey, false, 0x80),
+            (KeyStructure::ReaderKey, true, 0xA0),
+        ];
+        for (structure, into_nonvolatile, p1) in cases {
+            let options = LoadKeyOptions {
+                structure,
+                into_nonvolatile,
+            };
+            let bytes: Vec<u8> = PcscCommand::load_key_with_options(1, [0xFF; 6], options)
+                .try_into()
+                .unwrap();
+            assert_eq!(bytes[..5], [0xFF, 0x82, p1, 0x01, 0x06], "{options:?}");
+        }
+    }
+
+    #[test]
+    fn authenticate_status_error() {
+        let response = PcscResponse::try_from(&[0x69, 0x82][..]).unwrap();
+        let command = PcscCommand::general_authenticate(4, KeyType::MifareA, 0);
+        let error = PcscCodecError::status(response.sw(), command.ins_code());
+        assert!(matches!(
+            error,
+            PcscCodecError::StatusError {
+                sw: PcscStatusWords::CommandImpossible(0x82),
+                info: Some(PcscErrorCodeInfo::SecurityStatusUnsatisfied),
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Card returned status 69 82 (Security status not satisfied)"
+        );
+        assert_eq!(
+            PcscCodecError::status(PcscStatusWords::WrongLengthLe(0x10), 0xB0).to_string(),
+            "Card returned status 6C 10 (Wrong Le, SW2 gives the exact length)"
+        );
+    }
+
+    #[test]
+    fn uid_classification() {
+        let single = UidInfo::from(&[0x04, 0xA2, 0x3B, 0x11][..]);
+        assert!(!single.random);
+        let random = UidInfo::from(&[0x08, 0x5C, 0x91, 0xE0][..]);
+        assert!(random.random);
+        let double = UidInfo::from(&[0x08, 0x04, 0x5E, 0x72, 0x1A, 0x6B, 0x80][..]);
+        assert_eq!(double.uid.len(), 7);
+        assert!(!double.random);
+    }
+
+    #[test]
+    fn uid_with_sak_and_atqa() {
+        // MIFARE Classic 1K: UID, SAK 08, ATQA 00 04
+        let classic = UidDetails::split(&[0x04, 0xA2, 0x3B, 0x11, 0x08, 0x00, 0x04][..]);
+        assert_eq!(classic.uid.uid, [0x04, 0xA2, 0x3B, 0x11]);
+        assert_eq!(classic.sak, Some(0x08));
+        assert_eq!(classic.atqa, Some([0x00, 0x04]));
+        // Ultralight: double-size UID, SAK 00, ATQA 00 44
+        let ultralight =
+            UidDetails::split(&[0x04, 0x5E, 0x72, 0x1A, 0x6B, 0x80, 0x29, 0x00, 0x00, 0x44][..]);
+        assert_eq!(ultralight.uid.uid.len(), 7);
+        assert_eq!(ultralight.atqa, Some([0x00, 0x44]));
+        // Plain UIDs are left whole
+        for uid in [
+            &[0x04, 0xA2, 0x3B, 0x11][..],
+            &[0x08, 0x04, 0x5E, 0x72, 0x1A, 0x6B, 0x80][..],
+            &[0x04, 0x5E, 0x72, 0x1A, 0x6B, 0x80, 0x29][..],
+        ] {
+            let plain = UidDetails::split(uid);
+            assert_eq!(plain.uid.uid, uid);
+            assert_eq!((plain.sak, plain.atqa), (None, None));
+        }
+    }
+
+    #[test]
+    fn mutating_instructions() {
+        assert!(PcscCommand::update_binary(4, vec![0; 4])
+            .ins()
+            .is_mutating());
+        assert!(PcscCommand::load_key(0, [0xFF; 6]).ins().is_mutating());
+        assert!(PcscCommand::verify_pin(0x01, &[0x31, 0x32])
+            .ins()
+            .is_mutating());
+        assert!(PcscInstruction::ManageSession { data: vec![] }.is_mutating());
+        assert!(!PcscCommand::get_data(GetDataType::Uid).ins().is_mutating());
+        assert!(!PcscCommand::read_binary(4, 16).ins().is_mutating());
+        assert!(!PcscCommand::general_authenticate(4, KeyType::MifareA, 0)
+            .ins()
+            .is_mutating());
+    }
+
+    #[test]
+    fn iso15693_icode_sli_layout() {
+        // High data rate flag, as used with ICODE SLI tags
+        let read: Vec<u8> = PcscCommand::iso15693_read_single_block(0x02, 5)
+            .try_into()
+            .unwrap();
+        assert_eq!(read, [0xFF, 0x00, 0x00, 0x00, 0x03, 0x02, 0x20, 0x05]);
+        let read_multiple: Vec<u8> = PcscCommand::iso15693_read_multiple_blocks(0x02, 0, 4)
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            read_multiple,
+            [0xFF, 0x00, 0x00, 0x00, 0x04, 0x02, 0x23, 0x00, 0x03]
+        );
+        let write = PcscCommand::iso15693_write_single_block(0x42, 5, &[0xDE, 0xAD, 0xBE, 0xEF]);
+        let bytes: Vec<u8> = write.clone().try_into().unwrap();
+        assert_eq!(
+            bytes,
+            [0xFF, 0x00, 0x00, 0x00, 0x07, 0x42, 0x21, 0x05, 0xDE, 0xAD, 0xBE, 0xEF]
+        );
+        assert_eq!(PcscCommand::try_from(&bytes[..]).unwrap(), write);
+    }
+
+    #[test]
+    fn pcsc_error_source() {
+        fn transmit() -> Result<(), PcscCodecError> {
+            Err(PcscError::RemovedCard)?;
+            Ok(())
+        }
+        let error = transmit().unwrap_err();
+        assert!(matches!(
+            error,
+            PcscCodecError::Pcsc(PcscError::RemovedCard)
+        ));
+        assert_eq!(error.to_string(), "PC/SC error");
+        let source = std::error::Error::source(&error).unwrap();
+        assert_eq!(source.to_string(), PcscError::RemovedCard.to_string());
+    }
+
+    #[test]
+    fn synthetic_response_to_bytes() {
+        let response = PcscResponse::new(vec![0x04, 0xA2, 0x3B, 0x11], PcscStatusWords::Success);
+        assert_eq!(
+            Vec::from(response),
+            vec![0x04, 0xA2, 0x3B, 0x11, 0x90, 0x00]
+        );
+    }
+
+    #[test]
+    fn response_into_parts() {
+        let response = PcscResponse::try_from(&[0xDE, 0xAD, 0xBE, 0xEF, 0x90, 0x00][..]).unwrap();
+        let (data, sw) = response.into_parts();
+        assert_eq!(data, [0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(sw, PcscStatusWords::Success);
+        let (data, sw) = PcscResponse::try_from(&[0x6A, 0x82][..])
+            .unwrap()
+            .into_parts();
+        assert!(data.is_empty());
+        assert_eq!(sw, PcscStatusWords::CommandError(0x82));
+    }
+
+    #[test]
+    fn select_aid_layout() {
+        let ppse = b"2PAY.SYS.DDF01";
+        let command = PcscCommand::select_aid(ppse);
+        assert_eq!(command.cla(), 0x00);
+        let bytes: Vec<u8> = command.clone().try_into().unwrap();
+        assert_eq!(bytes[..5], [0x00, 0xA4, 0x04, 0x00, 0x0E]);
+        assert_eq!(&bytes[5..19], ppse);
+        assert_eq!(bytes[19], 0x00);
+        assert_eq!(PcscCommand::try_from(&bytes[..]).unwrap(), command);
+        assert!(matches!(
+            PcscCommand::try_from(&[0x00, 0xB0, 0x00, 0x00, 0x10][..]),
+            Err(PcscCodecError::WrongClass)
+        ));
+    }
+
+    #[test]
+    fn select_longest_round_trip() {
+        let command = PcscCommand::select_aid(&[0xA0; 255]);
+        let bytes: Vec<u8> = command.clone().try_into().unwrap();
+        assert_eq!(bytes.len(), PcscCommand::MAX_LENGTH);
+        assert_eq!(PcscCommand::try_from(&bytes[..]).unwrap(), command);
+    }
+
+    #[test]
+    fn status_into_result() {
+        assert!(PcscStatusWords::Success.into_result(0xB0).is_ok());
+        assert!(matches!(
+            PcscStatusWords::CommandImpossible(0x82).into_result(0xB0),
+            Err(PcscCodecError::StatusError {
+                sw: PcscStatusWords::CommandImpossible(0x82),
+                info: Some(PcscErrorCodeInfo::SecurityStatusUnsatisfied),
+            })
+        ));
+        assert!(matches!(
+            PcscStatusWords::WrongLength.into_result(0xB0),
+            Err(PcscCodecError::StatusError { info: None, .. })
+        ));
+    }
+
+    #[test]
+    fn decoded_info_equality() {
+        let read = PcscStatusWords::CommandImpossible(0x82).extra_info(0xB0);
+        let update = PcscStatusWords::CommandImpossible(0x82).extra_info(0xD6);
+        assert_eq!(read, Some(PcscErrorCodeInfo::SecurityStatusUnsatisfied));
+        assert_eq!(read, update);
+        assert_ne!(
+            PcscStatusWords::CommandError(0x82).extra_info(0xA4),
+            PcscStatusWords::CommandError(0x81).extra_info(0xA4)
+        );
+    }
+
+    #[test]
+    fn encoded_len_matches_encoding() {
+        let commands = [
+            PcscCommand::get_data(GetDataType::Uid),
+            PcscCommand::read_binary(4, 16),
+            PcscCommand::load_key(0, [0xFF; 6]),
+            PcscCommand::general_authenticate(4, KeyType::MifareB, 1),
+            PcscCommand::verify_pin(0x01, &[0x31, 0x32, 0x33, 0x34]),
+            PcscCommand::new(PcscInstruction::ManageSession { data: vec![] }, 0x00, 0x00),
+            PcscCommand::update_binary(4, vec![0; 16]),
+            PcscCommand::iso15693_read_multiple_blocks(0x02, 0, 4),
+            PcscCommand::select_aid(b"2PAY.SYS.DDF01"),
+        ];
+        for command in commands {
+            let len = command.encoded_len();
+            let bytes: Vec<u8> = command.try_into().unwrap();
+            assert_eq!(len, bytes.len());
+        }
+    }
+
+    #[test]
+    fn manage_session_layout() {
+        let start: Vec<u8> = PcscCommand::manage_session(&[0x81, 0x00])
+            .try_into()
+            .unwrap();
+        assert_eq!(start, [0xFF, 0xC2, 0x00, 0x00, 0x02, 0x81, 0x00]);
+        let switch: Vec<u8> = PcscCommand::switch_protocol(0x00, 0x04).try_into().unwrap();
+        assert_eq!(
+            switch,
+            [0xFF, 0xC2, 0x00, 0x02, 0x04, 0x8F, 0x02, 0x00, 0x04]
+        );
+    }
+
+    #[test]
+    fn every_two_byte_status() {
+        assert!(matches!(
+            PcscResponse::try_from(&[][..]),
+            Err(PcscCodecError::TooShort)
+        ));
+        assert!(matches!(
+            PcscResponse::try_from(&[0x90][..]),
+            Err(PcscCodecError::TooShort)
+        ));
+        for sw in 0..=u16::MAX {
+            let bytes = sw.to_be_bytes();
+            let response = PcscResponse::try_from(&bytes[..]).unwrap();
+            assert!(response.data().is_empty());
+            let with_data = PcscResponse::try_from(&[0xAA, bytes[0], bytes[1]][..]).unwrap();
+            assert_eq!(with_data.data(), [0xAA]);
+            assert_eq!(with_data.sw(), response.sw());
+            // Every status re-encodes to the same SW1
+            let encoded: Vec<u8> = response.into();
+            assert_eq!(encoded[0], bytes[0]);
+        }
+    }
+
+    #[test]
+    fn response_into_data() {
+        let response = PcscResponse::try_from(&[0x6F, 0x00, 0x90, 0x00][..]).unwrap();
+        assert_eq!(response.into_data(0xA4).unwrap(), [0x6F, 0x00]);
+        let response = PcscResponse::try_from(&[0x6A, 0x82][..]).unwrap();
+        assert!(matches!(
+            response.into_data(0xA4),
+            Err(PcscCodecError::StatusError {
+                sw: PcscStatusWords::CommandError(0x82),
+                info: Some(PcscErrorCodeInfo::FileNotFound),
+            })
+        ));
+    }
+
+    #[test]
+    fn execution_error_status() {
+        let response = PcscResponse::try_from(&[0x64, 0x00][..]).unwrap();
+        assert_eq!(response.sw(), PcscStatusWords::ExecutionError(0x00));
+        assert_eq!(
+            response.sw().extra_info(0xD6),
+            Some(PcscErrorCodeInfo::WritingFailed)
+        );
+        assert_eq!(response.sw().extra_info(0xB0), None);
+        assert_eq!(
+            PcscStatusWords::ExecutionError(0x01).extra_info(0xB0),
+            Some(PcscErrorCodeInfo::ImmediateResponseRequired)
+        );
+    }
+
+    #[test]
+    fn security_error_status() {
+        let response = PcscResponse::try_from(&[0x66, 0x69][..]).unwrap();
+        assert_eq!(response.sw(), PcscStatusWords::SecurityError(0x69));
+        assert_eq!(
+            response.sw().extra_info(0xB0),
+            Some(PcscErrorCodeInfo::IncorrectPadding)
+        );
+        assert_eq!(PcscStatusWords::SecurityError(0x00).extra_info(0xB0), None);
+    }
+
+    #[test]
+    fn status_words_bytes_round_trip() {
+        let all = [
+            PcscStatusWords::BytesRemaining(0x10),
+            PcscStatusWords::Warning(0x81),
+            PcscStatusWords::AllowedRetries(0x00),
+            PcscStatusWords::RetriesRemaining(3),
+            PcscStatusWords::ExecutionError(0x00),
+            PcscStatusWords::MemoryFailure(0x81),
+            PcscStatusWords::SecurityError(0x69),
+            PcscStatusWords::WrongLength,
+            PcscStatusWords::WrongClassByte,
+            PcscStatusWords::CommandImpossible(0x82),
+            PcscStatusWords::CommandError(0x82),
+            PcscStatusWords::WrongParameter,
+            PcscStatusWords::WrongLengthLe(0x04),
+            PcscStatusWords::Success,
+            PcscStatusWords::Unknown {
+                sw1: 0x6F,
+                sw2: 0x00,
+            },
+        ];
+        for sw in all {
+            let [sw1, sw2] = sw.to_bytes();
+            assert_eq!(PcscStatusWords::from_bytes(sw1, sw2), sw);
+        }
+        assert_eq!(PcscStatusWords::Success.to_bytes(), [0x90, 0x00]);
+        assert_eq!(
+            PcscStatusWords::from_bytes(0x63, 0xC2),
+            PcscStatusWords::RetriesRemaining(2)
+        );
+    }
+
+    #[test]
+    fn select_file_modes() {
+        let modes = [
+            (SelectMode::FileId, 0x00),
+            (SelectMode::ChildDf, 0x01),
+            (SelectMode::ChildEf, 0x02),
+            (SelectMode::ParentDf, 0x03),
+            (SelectMode::DfName, 0x04),
+            (SelectMode::PathFromMf, 0x08),
+            (SelectMode::PathFromCurrentDf, 0x09),
+        ];
+        for (mode, p1) in modes {
+            let bytes: Vec<u8> = PcscCommand::select_file(mode, &[0x3F, 0x00])
+                .try_into()
+                .unwrap();
+            assert_eq!(bytes, [0x00, 0xA4, p1, 0x00, 0x02, 0x3F, 0x00, 0x00]);
+        }
+        let parent = PcscCommand::select_file(SelectMode::ParentDf, &[]);
+        assert_eq!(parent.encoded_len(), 5);
+        let bytes: Vec<u8> = parent.clone().try_into().unwrap();
+        assert_eq!(bytes, [0x00, 0xA4, 0x03, 0x00, 0x00]);
+        assert_eq!(PcscCommand::try_from(&bytes[..]).unwrap(), parent);
+    }
+
+    #[test]
+    fn apdu_cases() {
+        let header = [0x00, 0xA4, 0x04, 0x00];
+        let case = |body: &[u8]| apdu_case(&[&header[..], body].concat());
+        assert_eq!(case(&[]).unwrap(), ApduCase::Case1);
+        assert_eq!(case(&[0x00]).unwrap(), ApduCase::Case2Short);
+        assert_eq!(case(&[0x02, 0x3F, 0x00]).unwrap(), ApduCase::Case3Short);
+        assert_eq!(
+            case(&[0x02, 0x3F, 0x00, 0x00]).unwrap(),
+            ApduCase::Case4Short
+        );
+        assert_eq!(case(&[0x00, 0x01, 0x00]).unwrap(), ApduCase::Case2Extended);
+        assert_eq!(
+            case(&[0x00, 0x00, 0x02, 0x3F, 0x00]).unwrap(),
+            ApduCase::Case3Extended
+        );
+        assert_eq!(
+            case(&[0x00, 0x00, 0x02, 0x3F, 0x00, 0x01, 0x00]).unwrap(),
+            ApduCase::Case4Extended
+        );
+        assert!(matches!(
+            apdu_case(&[0x00, 0xA4]),
+            Err(PcscCodecError::TooShort)
+        ));
+        assert!(matches!(
+            case(&[0x03, 0x3F, 0x00]),
+            Err(PcscCodecError::TooShort)
+        ));
+        assert!(matches!(
+            case(&[0x01, 0x3F, 0x00, 0x00]),
+            Err(PcscCodecError::TooLong)
+        ));
+        assert!(matches!(case(&[0x00, 0x10]), Err(PcscCodecError::ZeroLc)));
+        assert!(matches!(
+            case(&[0x00, 0x00, 0x00, 0x01, 0x00]),
+            Err(PcscCodecError::ZeroLc)
+        ));
     }
 }
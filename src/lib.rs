@@ -1,134 +1,2481 @@
 //#![deny(missing_docs)]
 
 //! `nfc-pcsc` - an implementation of the PC/SC workgroup ISO14443 and ISO15693 part 3 spec
+//!
+//! With the `logging` feature, every APDU sent and response received is logged in hex at trace
+//! level under the `nfc_pcsc::apdu` target.
 
+#[cfg(feature = "tokio")]
+mod async_reader;
 pub mod atr;
+pub mod cache;
 pub mod command;
+pub mod mifare;
+pub mod model;
+pub mod ndef;
+pub mod ops;
+pub mod snapshot;
+pub mod tlv;
+pub mod transport;
+#[cfg(feature = "ultralight-c")]
+mod ultralight;
 
-use atr::{CardName, Standard, TagType};
-use command::{PcscCodecError, PcscCommand, PcscResponse};
+#[cfg(feature = "tokio")]
+pub use async_reader::AsyncReader;
+
+use atr::{AtrInfo, CardName, PaymentNetwork, Standard, TagType};
+use cache::{CachedCard, CardCache};
+use command::{
+    apdu_case, ApduCase, GetDataParams, GetDataType, KeyType, LoadKeyOptions, PcscCodecError,
+    PcscCommand, PcscInstruction, PcscResponse, PcscStatusWords, SelectMode, UidDetails, UidInfo,
+};
+use model::ReaderModel;
+use ndef::{CapabilityContainer, NdefError, NdefRecord, Type4CapabilityContainer};
+use ops::CardOps;
+use transport::{ApduTransport, Connector};
+
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use pcsc::{
-    Card, Context, Error as PcscError, Protocols, ReaderState, Scope, ShareMode, State,
-    PNP_NOTIFICATION,
+    Attribute, Card, Context, Disposition, Error as PcscError, Protocols, ReaderState, Scope,
+    ShareMode, State, PNP_NOTIFICATION,
 };
 
-pub struct RfidTag {
-    tag_type: Option<TagType>,
-    standard: Option<Standard>,
-    card_name: Option<CardName>,
-    card: Card,
+const GET_RESPONSE: [u8; 4] = [0x00, 0xC0, 0x00, 0x00];
+// GET RESPONSE rounds after which a card still reporting 61xx is given up on; enough for the
+// largest extended length response at 256 bytes a round
+const MAX_GET_RESPONSE_ROUNDS: usize = 256;
+// Manage Session data objects, PC/SC 2.02 part 3 section 3.2.2.1
+const START_TRANSPARENT_SESSION: [u8; 2] = [0x81, 0x00];
+const END_TRANSPARENT_SESSION: [u8; 2] = [0x82, 0x00];
+// Generic error status response object: error byte, then the SW1 SW2 it maps to
+const SESSION_ERROR_STATUS: u32 = 0xC0;
+// Transparent Exchange response object holding the card's answer
+const SESSION_ICC_RESPONSE: u32 = 0x97;
+
+// ACS pseudo-APDU, answered with bare ASCII rather than data and status words
+const GET_FIRMWARE_VERSION: [u8; 5] = [0xFF, 0x00, 0x48, 0x00, 0x00];
+
+/// PC/SC errors readers report during brief RF glitches, retried by
+/// [`RfidTag::run_command_retry`]
+pub const TRANSIENT_ERRORS: &[PcscError] = &[
+    PcscError::ResetCard,
+    PcscError::RemovedCard,
+    PcscError::UnresponsiveCard,
+];
+
+// Delay between connection attempts while another process holds the card
+const SHARE_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+// Delay before the first retry, doubled on each further attempt
+const RETRY_DELAY: Duration = Duration::from_millis(10);
+
+#[cfg(feature = "logging")]
+const LOG_TARGET: &str = "nfc_pcsc::apdu";
+
+#[cfg(feature = "logging")]
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
-impl RfidTag {
+// Transmit through any transport, logging the exchange when enabled
+fn transmit<T: ApduTransport>(
+    transport: &T,
+    apdu: &[u8],
+    response_size: usize,
+) -> Result<Vec<u8>, PcscError> {
+    #[cfg(feature = "logging")]
+    log::trace!(target: LOG_TARGET, "> {}", hex(apdu));
+    let mut buf = Vec::with_capacity(response_size);
+    let result = transport.transmit(apdu, &mut buf).map(|()| buf);
+    #[cfg(feature = "logging")]
+    match &result {
+        Ok(response) => log::trace!(target: LOG_TARGET, "< {}", hex(response)),
+        Err(error) => log::trace!(target: LOG_TARGET, "< {error}"),
+    }
+    result
+}
+
+// Size a receive buffer for a command's expected response, never beyond what the reader can
+// deliver but always with room for the status words
+fn response_buffer_len(expected: usize, reader_max: Option<usize>) -> usize {
+    match reader_max {
+        Some(max) => expected.min(max.max(PcscResponse::MIN_LENGTH)),
+        None => expected,
+    }
+}
+
+fn response_size_for_le(le: u8) -> usize {
+    if le == 0 {
+        PcscResponse::MAX_LENGTH
+    } else {
+        le as usize + 2
+    }
+}
+
+// The APDU with its Le field, short or extended, rewritten to the short Le `le` (0 meaning 256);
+// `None` if it carries no Le
+fn with_le(apdu: &[u8], le: u8) -> Option<Vec<u8>> {
+    let mut retry = apdu.to_vec();
+    let len = retry.len();
+    match apdu_case(apdu).ok()? {
+        ApduCase::Case2Short | ApduCase::Case4Short => retry[len - 1] = le,
+        // Le is the final two bytes of an extended APDU
+        ApduCase::Case2Extended | ApduCase::Case4Extended => {
+            let le = if le == 0 { 0x100 } else { le as u16 };
+            retry[len - 2..].copy_from_slice(&le.to_be_bytes());
+        }
+        ApduCase::Case1 | ApduCase::Case3Short | ApduCase::Case3Extended => return None,
+    }
+    Some(retry)
+}
+
+// Transmit an APDU, re-issuing it once with the corrected Le on 6Cxx and following up with
+// GET RESPONSE while the card reports 61xx, for at most MAX_GET_RESPONSE_ROUNDS rounds
+fn transmit_chained(
+    mut transmit: impl FnMut(&[u8], usize) -> Result<Vec<u8>, PcscError>,
+    apdu: &[u8],
+    response_size: usize,
+) -> Result<PcscResponse, PcscCodecError> {
+    let response_bytes = transmit(apdu, response_size)?;
+    let mut response = PcscResponse::try_from(&response_bytes[..])?;
+    if let PcscStatusWords::WrongLengthLe(le) = response.sw() {
+        if let Some(retry) = with_le(apdu, le) {
+            let retry_bytes = transmit(&retry, response_size_for_le(le))?;
+            response = PcscResponse::try_from(&retry_bytes[..])?;
+        }
+    }
+    let mut rounds = 0;
+    while let PcscStatusWords::BytesRemaining(remaining) = response.sw() {
+        rounds += 1;
+        if rounds > MAX_GET_RESPONSE_ROUNDS {
+            return Err(PcscCodecError::TooLong);
+        }
+        let mut get_response = GET_RESPONSE.to_vec();
+        get_response.push(remaining);
+        let next_bytes = transmit(&get_response, response_size_for_le(remaining))?;
+        response.extend(PcscResponse::try_from(&next_bytes[..])?);
+    }
+    Ok(response)
+}
+
+/// A card connected through a reader.
+///
+/// `RfidTag` is `Send` and `Sync` whenever its transport is. [`pcsc::Card`] is both, so a
+/// connected tag can be handed to a worker thread as is, or shared between threads behind an
+/// [`Arc`]; PC/SC serializes the transmissions of concurrent callers.
+pub struct RfidTag<T = Card> {
+    atr_info: AtrInfo,
+    card: Arc<T>,
+    reader_model: ReaderModel,
+    // Largest message the reader can carry, if it reports one
+    max_response_len: Option<usize>,
+    uid_params: GetDataParams,
+}
+
+/// Summarises the tag from its cached ATR, without any card I/O
+impl<T> fmt::Display for RfidTag<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.atr_info.tag_type {
+            Some(tag_type) => write!(f, "{tag_type:?}")?,
+            None => f.write_str("Unrecognized tag")?,
+        }
+        if let Some(standard) = self.atr_info.standard {
+            write!(f, " {standard:?}")?;
+        }
+        if let Some(card_name) = self.atr_info.card_name {
+            write!(f, " {card_name:?}")?;
+        }
+        if let Some((first, rest)) = self.atr_info.atr.split_first() {
+            write!(f, " [{first:02X}")?;
+            for byte in rest {
+                write!(f, " {byte:02X}")?;
+            }
+            f.write_str("]")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: ApduTransport> RfidTag<T> {
+    /// Wrap an arbitrary transport, identifying the tag from `atr`
+    pub fn with_transport(transport: T, atr: &[u8]) -> Self {
+        Self {
+            atr_info: atr::parse_atr(atr),
+            card: Arc::new(transport),
+            reader_model: ReaderModel::Unknown,
+            max_response_len: None,
+            uid_params: GetDataParams::default(),
+        }
+    }
+
+    /// Replace what was recognized from the ATR, e.g. with one fetched after a reconnect
+    pub fn reparse_atr(&mut self, atr: &[u8]) {
+        self.atr_info = atr::parse_atr(atr);
+    }
+
+    /// P1 and P2 used to read the UID
+    pub fn uid_params(&self) -> GetDataParams {
+        self.uid_params
+    }
+
+    /// Read the UID with other Get Data parameters, for readers that deviate from PC/SC
+    pub fn set_uid_params(&mut self, params: GetDataParams) {
+        self.uid_params = params;
+    }
+
+    /// Warm reset the card by reconnecting with [`Disposition::ResetCard`], e.g. to recover a
+    /// desynchronized session, and re-parse the new ATR, which is returned. Fails with
+    /// [`PcscError::SharingViolation`] while a timed out [`RfidTag::send_apdu_timeout`] still
+    /// holds the card.
+    pub fn warm_reset(&mut self) -> Result<Vec<u8>, PcscError> {
+        let card = Arc::get_mut(&mut self.card).ok_or(PcscError::SharingViolation)?;
+        let atr = card.reconnect(Disposition::ResetCard)?;
+        self.reparse_atr(&atr);
+        Ok(atr)
+    }
+
+    /// Model of the reader the tag was found on
+    pub fn reader_model(&self) -> ReaderModel {
+        self.reader_model
+    }
+
+    pub fn atr_info(&self) -> &AtrInfo {
+        &self.atr_info
+    }
+
+    /// Whether anything was recognized from the ATR. Unrecognized tags still accept commands.
+    pub fn is_recognized(&self) -> bool {
+        let info = &self.atr_info;
+        info.tag_type.is_some() || info.standard.is_some() || info.card_name.is_some()
+    }
+
     pub fn tag_type(&self) -> Option<TagType> {
-        self.tag_type
+        self.atr_info.tag_type
     }
 
     pub fn standard(&self) -> Option<Standard> {
-        self.standard
+        self.atr_info.standard
     }
 
     pub fn card_name(&self) -> Option<CardName> {
-        self.card_name
+        self.atr_info.card_name
+    }
+
+    /// Historical bytes from the ATR, carrying e.g. the application identifier of ISO14443-4 cards
+    pub fn historical_bytes(&self) -> &[u8] {
+        &self.atr_info.historical_bytes
+    }
+
+    /// Block operations for this card's family, chosen from its ATR
+    pub fn ops(&self) -> &dyn CardOps<T> {
+        ops::for_card(self.card_name(), self.tag_type())
+    }
+
+    /// Payment scheme hinted at by the historical bytes; see [`atr::payment_network`]. A card
+    /// is only positively identified by selecting its payment application.
+    pub fn payment_network(&self) -> Option<PaymentNetwork> {
+        atr::payment_network(&self.atr_info.historical_bytes)
     }
 
     pub fn run_command(&self, command: PcscCommand) -> Result<PcscResponse, PcscCodecError> {
         let response_size = command.expected_response_len();
         let command_bytes: Vec<u8> = command.try_into()?;
-        let response_bytes = self
-            .send_apdu(&command_bytes, response_size)
-            .map_err(PcscCodecError::Pcsc)?;
+        let response_bytes = self.send_apdu(&command_bytes, response_size)?;
         let response = PcscResponse::try_from(&response_bytes[..])?;
         Ok(response)
     }
 
+    /// Like [`RfidTag::run_command`], with a receive buffer of exactly `response_capacity`
+    /// bytes, status words included, for responses the codec's estimate would truncate
+    pub fn run_command_with_capacity(
+        &self,
+        command: PcscCommand,
+        response_capacity: usize,
+    ) -> Result<PcscResponse, PcscCodecError> {
+        let command_bytes: Vec<u8> = command.try_into()?;
+        let response_bytes = transmit(&*self.card, &command_bytes, response_capacity)?;
+        PcscResponse::try_from(&response_bytes[..])
+    }
+
+    /// Run a command, retrying up to `retries` times on any of [`TRANSIENT_ERRORS`]. Other
+    /// errors are returned immediately.
+    pub fn run_command_retry(
+        &self,
+        command: PcscCommand,
+        retries: u32,
+    ) -> Result<PcscResponse, PcscCodecError> {
+        self.run_command_retry_on(command, retries, TRANSIENT_ERRORS)
+    }
+
+    /// Run a command, retrying up to `retries` times with a growing delay on any of `transient`
+    pub fn run_command_retry_on(
+        &self,
+        command: PcscCommand,
+        retries: u32,
+        transient: &[PcscError],
+    ) -> Result<PcscResponse, PcscCodecError> {
+        let mut delay = RETRY_DELAY;
+        for _ in 0..retries {
+            match self.run_command(command.clone()) {
+                Err(PcscCodecError::Pcsc(error)) if transient.contains(&error) => {
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+        self.run_command(command)
+    }
+
+    /// Run a command, treating any status other than success as an error
+    pub fn run_command_checked(
+        &self,
+        command: PcscCommand,
+    ) -> Result<PcscResponse, PcscCodecError> {
+        let ins = command.ins_code();
+        let response = self.run_command(command)?;
+        response.sw().into_result(ins)?;
+        Ok(response)
+    }
+
+    /// Read the card UID, flagging random single-size UIDs
+    pub fn uid_info(&self) -> Result<UidInfo, PcscCodecError> {
+        Ok(self.read_uid_full()?.uid)
+    }
+
+    /// Whether `other` presents the same UID, e.g. the same card seen on another reader.
+    ///
+    /// Cards with random UIDs present a new one on every activation, so they never compare equal
+    /// once re-activated.
+    pub fn same_card<U: ApduTransport>(&self, other: &RfidTag<U>) -> Result<bool, PcscCodecError> {
+        Ok(self.uid_info()?.uid == other.uid_info()?.uid)
+    }
+
+    /// Read the card UID along with the SAK and ATQA, for readers that append them to it and
+    /// have [`GetDataParams::split_sak_atqa`] set; otherwise the response is all UID
+    pub fn read_uid_full(&self) -> Result<UidDetails, PcscCodecError> {
+        let command = PcscCommand::get_data_with_params(self.uid_params);
+        let response = self.run_command_checked(command)?;
+        Ok(if self.uid_params.split_sak_atqa {
+            UidDetails::split(response.data())
+        } else {
+            UidDetails::uid_only(response.data())
+        })
+    }
+
+    /// Read the ISO14443-4 answer to select, starting with its TL byte; parse it with
+    /// [`Ats::parse`](atr::Ats::parse)
+    pub fn get_ats(&self) -> Result<Vec<u8>, PcscCodecError> {
+        self.run_command_checked(PcscCommand::get_data(GetDataType::Ats))
+            .map(|response| response.into_parts().0)
+    }
+
+    /// Read the ISO14443-A ATQA, as usually written (most significant byte first), on readers
+    /// offering it through Get Data
+    pub fn get_atqa(&self) -> Result<[u8; 2], PcscCodecError> {
+        let response = self.run_command_checked(PcscCommand::get_data(GetDataType::Atqa))?;
+        response
+            .data()
+            .try_into()
+            .map_err(|_| PcscCodecError::TooShort)
+    }
+
+    /// Read the ISO14443-A SAK on readers offering it through Get Data; see
+    /// [`atr::card_from_sak`] to identify the card from it
+    pub fn get_sak(&self) -> Result<u8, PcscCodecError> {
+        let response = self.run_command_checked(PcscCommand::get_data(GetDataType::Sak))?;
+        match response.data() {
+            [sak] => Ok(*sak),
+            [] => Err(PcscCodecError::TooShort),
+            _ => Err(PcscCodecError::TooLong),
+        }
+    }
+
+    /// Read one 16 byte block
+    pub fn read_block(&self, block: u8) -> Result<[u8; 16], PcscCodecError> {
+        let response = self.run_command_checked(PcscCommand::read_binary(block as u16, 16))?;
+        response
+            .data()
+            .try_into()
+            .map_err(|_| PcscCodecError::TooShort)
+    }
+
+    /// Read `total_len` bytes from consecutive 16 byte blocks starting at `start_block`,
+    /// stopping at the first block that fails
+    pub fn read_range(&self, start_block: u8, total_len: usize) -> Result<Vec<u8>, PcscCodecError> {
+        self.read_range_in_blocks(start_block, total_len, 16)
+    }
+
+    /// Like [`RfidTag::read_range`] for cards whose blocks are not 16 bytes, e.g. the 4 byte
+    /// blocks of ISO 15693 tags, reading `block_len` bytes per address
+    pub fn read_range_in_blocks(
+        &self,
+        start_block: u8,
+        total_len: usize,
+        block_len: u8,
+    ) -> Result<Vec<u8>, PcscCodecError> {
+        // An Le of 0 would ask for 256 bytes, not read nothing
+        if block_len == 0 {
+            return Err(PcscCodecError::TooShort);
+        }
+        let len = block_len as usize;
+        let mut data = Vec::with_capacity(total_len.next_multiple_of(len));
+        let mut address = start_block as u16;
+        while data.len() < total_len {
+            let response =
+                self.run_command_checked(PcscCommand::read_binary(address, block_len))?;
+            if response.data().len() < len {
+                return Err(PcscCodecError::TooShort);
+            }
+            data.extend_from_slice(&response.data()[..len]);
+            address = address
+                .checked_add(1)
+                .ok_or(PcscCodecError::AddressOutOfRange)?;
+        }
+        data.truncate(total_len);
+        Ok(data)
+    }
+
+    /// Read from a MIFARE Ultralight/NTAG page. The tag always answers with a four page window,
+    /// so the 16 bytes returned cover `page` through `page + 3`.
+    pub fn read_page(&self, page: u8) -> Result<[u8; 16], PcscCodecError> {
+        self.read_block(page)
+    }
+
+    /// Write a single MIFARE Ultralight/NTAG page
+    pub fn write_page(&self, page: u8, data: [u8; 4]) -> Result<(), PcscCodecError> {
+        self.run_command_checked(PcscCommand::update_binary(page as u16, data.to_vec()))?;
+        Ok(())
+    }
+
+    /// Load a MIFARE key into the reader's volatile key slot `slot`
+    pub fn load_key(&self, slot: u8, key: [u8; 6]) -> Result<(), PcscCodecError> {
+        self.load_key_with_options(slot, key, LoadKeyOptions::default())
+    }
+
+    /// Like [`RfidTag::load_key`], e.g. persisting the key in non-volatile reader memory
+    pub fn load_key_with_options(
+        &self,
+        slot: u8,
+        key: [u8; 6],
+        options: LoadKeyOptions,
+    ) -> Result<(), PcscCodecError> {
+        self.run_command_checked(PcscCommand::load_key_with_options(slot, key, options))?;
+        Ok(())
+    }
+
+    /// Read every block of a MIFARE Classic card that one of `keys` gives access to, as
+    /// `(block, data)` pairs. Each key is tried as key A then key B; sectors no key opens and
+    /// blocks the access conditions hide are skipped.
+    pub fn dump(&self, keys: &[[u8; 6]]) -> Result<Vec<(u8, Vec<u8>)>, PcscCodecError> {
+        let sectors = match self.card_name() {
+            Some(CardName::MifareMini) => 5,
+            Some(
+                CardName::MifareStandard4K
+                | CardName::MifarePlusSl1_4K
+                | CardName::MifarePlusSl2_4K,
+            ) => 40,
+            _ => 16,
+        };
+        let mut blocks = vec![];
+        for sector in 0..sectors {
+            let Some((first, count)) = mifare::sector_blocks(sector) else {
+                break;
+            };
+            if !self.try_keys(first, keys)? {
+                continue;
+            }
+            for block in (0..count).map(|offset| first + offset) {
+                match self.read_block(block) {
+                    Ok(data) => blocks.push((block, data.to_vec())),
+                    Err(PcscCodecError::StatusError { .. }) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(blocks)
+    }
+
+    // Authenticate `block` with the first of `keys` the card accepts, as key A or key B
+    fn try_keys(&self, block: u8, keys: &[[u8; 6]]) -> Result<bool, PcscCodecError> {
+        for key in keys {
+            self.load_key(0, *key)?;
+            for key_type in [KeyType::MifareA, KeyType::MifareB] {
+                let auth = PcscCommand::general_authenticate(block as u16, key_type, 0);
+                match self.run_command_checked(auth) {
+                    Ok(_) => return Ok(true),
+                    Err(PcscCodecError::StatusError { .. }) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Write MIFARE Classic blocks in order, loading and authenticating with each block's own
+    /// key first. Stops at the first failure, reported as [`PcscCodecError::Block`].
+    pub fn provision(
+        &self,
+        plan: &[(u8, [u8; 6], KeyType, Vec<u8>)],
+    ) -> Result<(), PcscCodecError> {
+        for (block, key, key_type, data) in plan {
+            let write = || {
+                self.load_key(0, *key)?;
+                self.run_command_checked(PcscCommand::general_authenticate(
+                    *block as u16,
+                    *key_type,
+                    0,
+                ))?;
+                self.run_command_checked(PcscCommand::update_binary(*block as u16, data.clone()))
+            };
+            write().map_err(|source| PcscCodecError::Block {
+                block: *block,
+                source: Box::new(source),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Authenticate a MIFARE Classic sector with the key loaded in `key_slot` and read all of its
+    /// blocks, sector trailer last. Sectors 32-39 of a 4K card yield 16 blocks, all others 4.
+    pub fn read_sector(
+        &self,
+        sector: u8,
+        key_type: KeyType,
+        key_slot: u8,
+    ) -> Result<Vec<[u8; 16]>, PcscCodecError> {
+        let (first, count) =
+            mifare::sector_blocks(sector).ok_or(PcscCodecError::AddressOutOfRange)?;
+        self.run_command_checked(PcscCommand::general_authenticate(
+            first as u16,
+            key_type,
+            key_slot,
+        ))?;
+        (0..count)
+            .map(|offset| self.read_block(first + offset))
+            .collect()
+    }
+
+    /// Read the NDEF message from a Type 2 (Ultralight/NTAG) tag
+    pub fn read_ndef(&self) -> Result<Vec<NdefRecord>, NdefError> {
+        // Page 3 holds the capability container, the data area starts at page 4
+        let window = self.read_page(3)?;
+        let capacity = ndef::data_area_len(&window)?;
+        let mut area = window[4..].to_vec();
+        let mut page: u8 = 7;
+        loop {
+            area.truncate(capacity);
+            match ndef::find_message(&area) {
+                Err(NdefError::Truncated) if area.len() < capacity => {
+                    area.extend(self.read_page(page)?);
+                    page = page.checked_add(4).ok_or(NdefError::Truncated)?;
+                }
+                message => return ndef::parse_message(message?),
+            }
+        }
+    }
+
+    /// Read the NDEF message of a Type 4 tag from the file its capability container names
+    pub fn read_ndef_type4(&self) -> Result<Vec<NdefRecord>, NdefError> {
+        self.run_command_checked(PcscCommand::select_aid(&ndef::TYPE4_NDEF_AID))?;
+        self.select_ef(ndef::TYPE4_CC_FILE)?;
+        let cc = Type4CapabilityContainer::parse(&self.read_binary_iso(0, 15)?)?;
+        self.select_ef(cc.ndef_file_id)?;
+        let nlen = match self.read_binary_iso(0, 2)?[..] {
+            [msb, lsb] => u16::from_be_bytes([msb, lsb]),
+            _ => return Err(NdefError::Truncated),
+        };
+        if nlen == 0 {
+            return Err(NdefError::NoMessage);
+        }
+        // The message must fit in the file after NLEN, and within the 15 bit offsets Read Binary
+        // can address; P1 bit 8 set would select by short file identifier instead
+        if nlen > cc.ndef_file_len.saturating_sub(2) || nlen > ndef::TYPE4_MAX_OFFSET - 1 {
+            return Err(NdefError::CapacityExceeded);
+        }
+        let chunk = cc.max_read_len.clamp(1, 0xFF);
+        let mut message = Vec::with_capacity(nlen as usize);
+        while message.len() < nlen as usize {
+            let read = message.len() as u16;
+            let offset = read.checked_add(2).ok_or(NdefError::CapacityExceeded)?;
+            let data = self.read_binary_iso(offset, (nlen - read).min(chunk) as u8)?;
+            if data.is_empty() {
+                return Err(NdefError::Truncated);
+            }
+            message.extend(data);
+        }
+        message.truncate(nlen as usize);
+        ndef::parse_message(&message)
+    }
+
+    // Select an elementary file by identifier without asking for its FCI
+    fn select_ef(&self, id: [u8; 2]) -> Result<(), PcscCodecError> {
+        self.run_command_checked(PcscCommand::new(
+            PcscInstruction::Select { data: id.to_vec() },
+            SelectMode::FileId.p1(),
+            0x0C,
+        ))?;
+        Ok(())
+    }
+
+    // Interindustry (CLA 00) Read Binary of the currently selected file, as Type 4 tags expect
+    // rather than the reader's FF pseudo-APDU
+    fn read_binary_iso(&self, offset: u16, le: u8) -> Result<Vec<u8>, PcscCodecError> {
+        let [p1, p2] = offset.to_be_bytes();
+        let response = self.send_apdu(&[0x00, 0xB0, p1, p2, le], response_size_for_le(le))?;
+        PcscResponse::try_from(&response[..])?.into_data(0xB0)
+    }
+
+    /// Read and parse the capability container in page 3 of a Type 2 (Ultralight/NTAG) tag
+    pub fn read_capability_container(&self) -> Result<CapabilityContainer, NdefError> {
+        CapabilityContainer::parse(&self.read_page(3)?)
+    }
+
+    /// Write an NDEF message to a Type 2 (Ultralight/NTAG) tag
+    pub fn write_ndef(&self, records: &[NdefRecord]) -> Result<(), NdefError> {
+        let cc = self.read_capability_container()?;
+        if !cc.can_write() {
+            return Err(NdefError::ReadOnly);
+        }
+        let area = ndef::encode_area(records, cc.data_area_len)?;
+        for (page, data) in (4..).zip(area.chunks(4)) {
+            let mut page_data = [0; 4];
+            page_data[..data.len()].copy_from_slice(data);
+            self.write_page(page, page_data)?;
+        }
+        Ok(())
+    }
+
+    /// Send Manage Session data objects, checking the generic error status object (C0) nested
+    /// in the response as well as the outer status words. Readers known not to implement
+    /// Manage Session are refused with [`PcscCodecError::UnsupportedReader`] before anything is
+    /// sent, as are the transparent session helpers built on it.
+    pub fn manage_session(&self, objects: &[u8]) -> Result<PcscResponse, PcscCodecError> {
+        self.check_manage_session()?;
+        let command = PcscCommand::manage_session(objects);
+        let ins = command.ins_code();
+        let response = self.run_command_checked(command)?;
+        session_status(&response, ins)?;
+        Ok(response)
+    }
+
+    /// Take manual control of the RF field and framing, bypassing the storage card abstraction
+    pub fn start_transparent_session(&self) -> Result<(), PcscCodecError> {
+        self.manage_session(&START_TRANSPARENT_SESSION)?;
+        Ok(())
+    }
+
+    pub fn end_transparent_session(&self) -> Result<(), PcscCodecError> {
+        self.manage_session(&END_TRANSPARENT_SESSION)?;
+        Ok(())
+    }
+
+    /// Exchange a raw frame with the card inside a transparent session, returning its answer
+    pub fn transceive(&self, frame: &[u8]) -> Result<Vec<u8>, PcscCodecError> {
+        self.check_manage_session()?;
+        if frame.len() > 0x7F {
+            return Err(PcscCodecError::TooLong);
+        }
+        let command = PcscCommand::transparent_exchange(frame);
+        let ins = command.ins_code();
+        let response = self.run_command_checked(command)?;
+        session_status(&response, ins)?;
+        response
+            .tlv()
+            .find(|(tag, _)| *tag == SESSION_ICC_RESPONSE)
+            .map(|(_, value)| value.to_vec())
+            .ok_or(PcscCodecError::TooShort)
+    }
+
+    /// Run the MIFARE Ultralight C 3DES mutual authentication with a 16 byte 2-key 3DES key,
+    /// over a transparent session. Protected pages can be read once it succeeds.
+    #[cfg(feature = "ultralight-c")]
+    pub fn authenticate_ultralight_c(&self, key: [u8; 16]) -> Result<(), PcscCodecError> {
+        let mut rnd_a = [0; 8];
+        getrandom::fill(&mut rnd_a).map_err(|_| PcscError::InternalError)?;
+        self.authenticate_ultralight_c_with(&key, rnd_a)
+    }
+
+    #[cfg(feature = "ultralight-c")]
+    fn authenticate_ultralight_c_with(
+        &self,
+        key: &[u8; 16],
+        rnd_a: [u8; 8],
+    ) -> Result<(), PcscCodecError> {
+        self.start_transparent_session()?;
+        let authenticated = (|| {
+            let challenge = self.transceive(&ultralight::AUTHENTICATE)?;
+            let Some((&ultralight::AUTHENTICATE_CONTINUE, ek_rnd_b)) = challenge.split_first()
+            else {
+                return Err(PcscCodecError::AuthenticationFailed);
+            };
+            let ek_rnd_b = ek_rnd_b
+                .try_into()
+                .map_err(|_| PcscCodecError::AuthenticationFailed)?;
+            let (token, iv) = ultralight::challenge_response(key, ek_rnd_b, rnd_a);
+            let mut frame = vec![ultralight::AUTHENTICATE_CONTINUE];
+            frame.extend_from_slice(&token);
+            let answer = self.transceive(&frame)?;
+            match answer.split_first() {
+                Some((0x00, ek_rnd_a))
+                    if ek_rnd_a.try_into().is_ok_and(|ek_rnd_a| {
+                        ultralight::verify_response(key, iv, ek_rnd_a, rnd_a)
+                    }) =>
+                {
+                    Ok(())
+                }
+                _ => Err(PcscCodecError::AuthenticationFailed),
+            }
+        })();
+        // Close the session whatever happened, but report the authentication error first
+        let ended = self.end_transparent_session();
+        authenticated.and(ended)
+    }
+
+    /// Switch the protocol used inside a transparent session
+    pub fn switch_protocol(&self, protocol: u8, layer: u8) -> Result<(), PcscCodecError> {
+        self.check_manage_session()?;
+        let command = PcscCommand::switch_protocol(protocol, layer);
+        let ins = command.ins_code();
+        let response = self.run_command_checked(command)?;
+        session_status(&response, ins)
+    }
+
+    fn check_manage_session(&self) -> Result<(), PcscCodecError> {
+        if self.reader_model.supports_manage_session() {
+            Ok(())
+        } else {
+            Err(PcscCodecError::UnsupportedReader(self.reader_model))
+        }
+    }
+
+    /// Select an application by AID. A card without the application answers 6A 82, returned
+    /// as a [`PcscCodecError::StatusError`] with [`command::PcscErrorCodeInfo::FileNotFound`].
+    pub fn select_application(&self, aid: &[u8]) -> Result<PcscResponse, PcscCodecError> {
+        let command = PcscCommand::select_aid(aid);
+        let ins = command.ins_code();
+        let response = self.run_command_full(command)?;
+        response.sw().into_result(ins)?;
+        Ok(response)
+    }
+
+    /// Ask the reader for its firmware version, e.g. `ACR122U207`. Only ACS readers (ACR122U
+    /// and relatives) implement this; others answer with an error status, reported as
+    /// [`PcscError::UnsupportedFeature`]. Readers known not to implement it are refused with
+    /// [`PcscCodecError::UnsupportedReader`] before anything is sent.
+    pub fn reader_firmware_version(&self) -> Result<String, PcscCodecError> {
+        if !self.reader_model.supports_firmware_version() {
+            return Err(PcscCodecError::UnsupportedReader(self.reader_model));
+        }
+        let response = self.send_apdu(&GET_FIRMWARE_VERSION, PcscResponse::MAX_LENGTH)?;
+        // Some firmwares append a success status after the text
+        let text = response.strip_suffix(&[0x90, 0x00]).unwrap_or(&response);
+        if text.is_empty() || !text.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+            return Err(PcscError::UnsupportedFeature.into());
+        }
+        Ok(String::from_utf8_lossy(text).into_owned())
+    }
+
+    /// Run a command, correcting a rejected Le (6Cxx) once and collecting any remaining data the
+    /// card signals with 61xx
+    pub fn run_command_full(&self, command: PcscCommand) -> Result<PcscResponse, PcscCodecError> {
+        let response_size = command.expected_response_len();
+        let command_bytes: Vec<u8> = command.try_into()?;
+        transmit_chained(
+            |apdu, size| self.send_apdu(apdu, size),
+            &command_bytes,
+            response_size,
+        )
+    }
+
+    /// Check the card is still connected, asking the reader rather than the card. A removed,
+    /// reset or unresponsive card gives `false`.
+    pub fn ping(&self) -> Result<bool, PcscError> {
+        match self.card.is_present() {
+            Err(
+                PcscError::RemovedCard
+                | PcscError::ResetCard
+                | PcscError::NoSmartcard
+                | PcscError::UnpoweredCard
+                | PcscError::UnresponsiveCard,
+            ) => Ok(false),
+            result => result,
+        }
+    }
+
+    /// Send raw APDUs in order, collecting each response. A failed command does not stop the
+    /// ones after it; error status words are returned as responses.
+    pub fn run_script(&self, commands: &[Vec<u8>]) -> Vec<Result<PcscResponse, PcscCodecError>> {
+        commands
+            .iter()
+            .map(|apdu| {
+                let response = self.send_apdu(apdu, PcscResponse::MAX_LENGTH)?;
+                PcscResponse::try_from(&response[..])
+            })
+            .collect()
+    }
+
+    /// Read a reader or card attribute, e.g. [`Attribute::MaxIfsd`] or
+    /// [`Attribute::VendorName`]
+    pub fn get_attribute(&self, attribute: Attribute) -> Result<Vec<u8>, PcscError> {
+        self.card.get_attribute(attribute)
+    }
+
+    /// Set a reader or card attribute; which ones are writable depends on the reader driver
+    pub fn set_attribute(&self, attribute: Attribute, value: &[u8]) -> Result<(), PcscError> {
+        self.card.set_attribute(attribute, value)
+    }
+
     pub fn send_apdu(&self, apdu: &[u8], response_size: usize) -> Result<Vec<u8>, PcscError> {
-        let mut buf = Vec::with_capacity(response_size);
-        self.card.transmit(apdu, &mut buf)?;
-        Ok(buf)
+        let response_size = response_buffer_len(response_size, self.max_response_len);
+        transmit(&*self.card, apdu, response_size)
+    }
+
+    /// Like [`RfidTag::send_apdu`], failing with [`PcscCodecError::ResponseTooShort`] on a
+    /// response of fewer than `min_len` bytes
+    pub fn send_apdu_checked(
+        &self,
+        apdu: &[u8],
+        min_len: usize,
+    ) -> Result<Vec<u8>, PcscCodecError> {
+        let response = self.send_apdu(apdu, PcscResponse::MAX_LENGTH.max(min_len))?;
+        if response.len() < min_len {
+            return Err(PcscCodecError::ResponseTooShort {
+                len: response.len(),
+                min_len,
+            });
+        }
+        Ok(response)
+    }
+}
+
+impl<T: ApduTransport + Send + Sync + 'static> RfidTag<T> {
+    /// Like [`RfidTag::send_apdu`], giving up with [`PcscError::Timeout`] after `timeout`.
+    ///
+    /// PC/SC defines no attribute for an I/O timeout, so the transmit runs on a helper thread
+    /// instead. On expiry that thread keeps a handle to the card until the reader answers or
+    /// gives up itself, and its response is discarded.
+    pub fn send_apdu_timeout(
+        &self,
+        apdu: &[u8],
+        response_size: usize,
+        timeout: Duration,
+    ) -> Result<Vec<u8>, PcscError> {
+        let card = Arc::clone(&self.card);
+        let apdu = apdu.to_vec();
+        let response_size = response_buffer_len(response_size, self.max_response_len);
+        with_timeout(timeout, move || transmit(&*card, &apdu, response_size))
     }
 }
 
+impl RfidTag {
+    /// Send a reader escape command through `SCardControl`. Control codes are reader specific;
+    /// build them with [`pcsc::ctl_code`] from the code given in the reader's documentation.
+    pub fn control(&self, control_code: u32, send: &[u8]) -> Result<Vec<u8>, PcscError> {
+        ApduTransport::control(&*self.card, control_code, send)
+    }
+
+    /// Query the ATR from the card itself rather than the reader-state snapshot
+    pub fn get_atr(&self) -> Result<Vec<u8>, PcscError> {
+        match self.card.status2_owned() {
+            Ok(status) if !status.atr().is_empty() => Ok(status.atr().to_vec()),
+            _ => self.card.get_attribute_owned(Attribute::AtrString),
+        }
+    }
+
+    // Cache the reader's maximum message size (SCARD_ATTR_MAXINPUT) for sizing transmit buffers
+    fn query_max_response_len(&mut self) {
+        self.max_response_len = self
+            .card
+            .get_attribute_owned(Attribute::Maxinput)
+            .ok()
+            .and_then(|value| Some(u32::from_le_bytes(value.get(..4)?.try_into().ok()?)))
+            .filter(|&len| len > 0)
+            .map(|len| len as usize);
+    }
+
+    // Some readers populate the ATR late, so retry the parse against the card
+    fn reparse_if_unknown(&mut self) {
+        if self.atr_info.tag_type.is_some() {
+            return;
+        }
+        if let Ok(atr) = self.get_atr() {
+            self.reparse_atr(&atr);
+        }
+    }
+}
+
+// Run a blocking PC/SC call on a helper thread, giving up with `PcscError::Timeout` after
+// `timeout`. A call that overruns finishes in the background and its result is dropped.
+fn with_timeout<R: Send + 'static>(
+    timeout: Duration,
+    call: impl FnOnce() -> Result<R, PcscError> + Send + 'static,
+) -> Result<R, PcscError> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(call());
+    });
+    receiver
+        .recv_timeout(timeout)
+        .unwrap_or(Err(PcscError::Timeout))
+}
+
+// Fail on a generic error status object reporting anything other than no error
+fn session_status(response: &PcscResponse, ins: u8) -> Result<(), PcscCodecError> {
+    for (tag, value) in response.tlv() {
+        if let (SESSION_ERROR_STATUS, &[error, sw1, sw2]) = (tag, value) {
+            if error != 0x00 {
+                PcscStatusWords::from_bytes(sw1, sw2).into_result(ins)?;
+            }
+        }
+    }
+    Ok(())
+}
+
 pub struct Reader {
     context: Context,
     state: [ReaderState; 1],
     is_alive: bool,
+    protocols: Protocols,
 }
 
 impl Reader {
+    /// Wait for the reader state to change, connecting to a newly presented card. Several cards
+    /// in the field at once are not connected to; [`Reader::get_card_status`] reports them as
+    /// [`CardPoll::Collision`].
     pub fn get_card(&mut self) -> Result<Option<RfidTag>, PcscError> {
+        self.get_card_sharing(Duration::ZERO)
+    }
+
+    /// Like [`Reader::get_card`], telling "no new card" apart from a collision and from the
+    /// reader having gone away without matching on error types
+    pub fn get_card_status(&mut self) -> Result<CardPoll, PcscError> {
+        match self.poll_card(Duration::ZERO) {
+            Err(PcscError::ReaderUnavailable) => Ok(CardPoll::ReaderGone),
+            result => result,
+        }
+    }
+
+    /// Turn the reader into an iterator of presented cards. Each `next()` blocks until a card
+    /// arrives. Errors are yielded without ending iteration, which ends once the reader is gone.
+    pub fn into_card_iter(mut self) -> impl Iterator<Item = Result<RfidTag, PcscError>> {
+        cards(move || self.get_card_status())
+    }
+
+    /// Like [`Reader::get_card`], but while another process holds the card exclusively keep
+    /// retrying the connection on [`PcscError::SharingViolation`] for up to `timeout`
+    pub fn get_card_waiting_for_share(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<RfidTag>, PcscError> {
+        self.get_card_sharing(timeout)
+    }
+
+    fn get_card_sharing(&mut self, share_timeout: Duration) -> Result<Option<RfidTag>, PcscError> {
+        match self.poll_card(share_timeout)? {
+            CardPoll::Card(tag) => Ok(Some(tag)),
+            CardPoll::NoCard | CardPoll::Collision | CardPoll::ReaderGone => Ok(None),
+        }
+    }
+
+    fn poll_card(&mut self, share_timeout: Duration) -> Result<CardPoll, PcscError> {
         if !self.is_alive {
             return Err(PcscError::ReaderUnavailable);
         }
         self.context.get_status_change(None, &mut self.state)?;
         let event = self.state[0].event_state();
-        if event.intersects(State::UNKNOWN | State::IGNORE) {
+        let poll = state_change(self.state[0].current_state(), event, || {
+            let card = connect_shared(
+                &self.context,
+                self.state[0].name(),
+                self.protocols,
+                share_timeout,
+            )?;
+            let mut tag = RfidTag {
+                atr_info: atr::parse_atr(self.state[0].atr()),
+                card: Arc::new(card),
+                reader_model: self.model(),
+                max_response_len: None,
+                uid_params: GetDataParams::default(),
+            };
+            tag.reparse_if_unknown();
+            tag.query_max_response_len();
+            Ok(tag)
+        })?;
+        if matches!(poll, CardPoll::ReaderGone) {
             self.is_alive = false;
         }
-        let card = if event != self.state[0].current_state() {
-            if event == State::PRESENT {
-                let card = self.context.connect(
-                    self.state[0].name(),
-                    ShareMode::Shared,
-                    Protocols::ANY,
-                )?;
-                let (tag_type, standard, card_name) = atr::parse_atr(self.state[0].atr());
-                Some(RfidTag {
-                    tag_type,
-                    standard,
-                    card_name,
-                    card,
-                })
-            } else {
-                None
-            }
-        } else {
-            None
-        };
         self.state[0].sync_current_state();
-        Ok(card)
+        Ok(poll)
+    }
+
+    /// Like [`Reader::get_card`], also reading the UID of a new card and returning it with any
+    /// data `cache` holds for it
+    pub fn get_card_cached<'c, V>(
+        &mut self,
+        cache: &'c CardCache<V>,
+    ) -> Result<Option<CachedCard<'c, V>>, PcscCodecError> {
+        let Some(tag) = self.get_card()? else {
+            return Ok(None);
+        };
+        let (uid, cached) = cache.lookup(&tag)?;
+        Ok(Some(CachedCard { tag, uid, cached }))
+    }
+
+    /// Check for a card in the reader without connecting to it, so a card held exclusively by
+    /// another process can still be detected. Does not block or consume the change that
+    /// [`Reader::get_card`] waits for.
+    pub fn card_present(&mut self) -> Result<bool, PcscError> {
+        Ok(self.card_presence()? != CardPresence::NoCard)
+    }
+
+    /// Like [`Reader::card_present`], telling a single card apart from a collision
+    pub fn card_presence(&mut self) -> Result<CardPresence, PcscError> {
+        if !self.is_alive {
+            return Err(PcscError::ReaderUnavailable);
+        }
+        // An UNAWARE query returns the current state immediately
+        let mut state = [ReaderState::new(self.state[0].name(), State::UNAWARE)];
+        self.context.get_status_change(None, &mut state)?;
+        match card_in_slot(state[0].event_state()) {
+            Some(present) => Ok(present),
+            None => {
+                self.is_alive = false;
+                Err(PcscError::ReaderUnavailable)
+            }
+        }
     }
 
     pub fn state(&self) -> State {
         self.state[0].current_state()
     }
+
+    /// Reader model recognized from the reader name
+    pub fn model(&self) -> ReaderModel {
+        ReaderModel::from_name(&self.state[0].name().to_string_lossy())
+    }
+
+    /// Send a reader escape command over a direct connection to the reader, which needs no
+    /// card in the field, e.g. to configure the reader. See [`RfidTag::control`] for control
+    /// codes. Some drivers only accept escape commands once enabled in their configuration.
+    pub fn control_direct(&mut self, control_code: u32, send: &[u8]) -> Result<Vec<u8>, PcscError> {
+        if !self.is_alive {
+            return Err(PcscError::ReaderUnavailable);
+        }
+        control_direct(&self.context, self.state[0].name(), control_code, send)
+    }
+
+    /// Protocols the present card offers in its ATR, read through a direct connection so no
+    /// protocol has to be negotiated first
+    pub fn card_protocols(&mut self) -> Result<Protocols, PcscError> {
+        if !self.is_alive {
+            return Err(PcscError::ReaderUnavailable);
+        }
+        let card = self.context.connect(
+            self.state[0].name(),
+            ShareMode::Direct,
+            Protocols::UNDEFINED,
+        )?;
+        match card.status2_owned()?.atr() {
+            [] => Err(PcscError::NoSmartcard),
+            atr => Ok(atr::supported_protocols(atr)),
+        }
+    }
+
+    /// Whether this is the SAM slot of a dual-interface reader, whose always present ICC is
+    /// not a contactless card
+    pub fn is_sam(&self) -> bool {
+        model::is_sam_slot(&self.state[0].name().to_string_lossy())
+    }
+
+    /// Protocols offered when connecting to a card, [`Protocols::ANY`] by default
+    pub fn protocols(&self) -> Protocols {
+        self.protocols
+    }
+
+    /// Pin the protocols offered when connecting, e.g. to force T=1 on cards that misbehave
+    /// under T=0
+    pub fn set_protocols(&mut self, protocols: Protocols) {
+        self.protocols = protocols;
+    }
+}
+
+/// Outcome of [`Reader::get_card_status`]
+pub enum CardPoll<T = Card> {
+    /// The reader state changed without a new card to connect to
+    NoCard,
+    Card(RfidTag<T>),
+    /// Several cards entered the field at once and none was connected to; see
+    /// [`CardPresence::Collision`]
+    Collision,
+    /// The reader was removed; fetch the readers again with [`Pcsc::get_readers`]
+    ReaderGone,
+}
+
+// Poll until a card or an error, stopping for good once the reader is gone
+fn cards<T>(
+    mut poll: impl FnMut() -> Result<CardPoll<T>, PcscError>,
+) -> impl Iterator<Item = Result<RfidTag<T>, PcscError>> {
+    let mut gone = false;
+    std::iter::from_fn(move || {
+        while !gone {
+            match poll() {
+                Ok(CardPoll::Card(tag)) => return Some(Ok(tag)),
+                Ok(CardPoll::NoCard | CardPoll::Collision) => {}
+                Ok(CardPoll::ReaderGone) => gone = true,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+        None
+    })
+}
+
+// What a reader state change from `current` to `event` means, calling `connect` only for a
+// newly presented single card
+fn state_change<T>(
+    current: State,
+    event: State,
+    connect: impl FnOnce() -> Result<RfidTag<T>, PcscError>,
+) -> Result<CardPoll<T>, PcscError> {
+    match card_in_slot(event) {
+        None => Ok(CardPoll::ReaderGone),
+        Some(_) if event == current => Ok(CardPoll::NoCard),
+        Some(CardPresence::Card) => connect().map(CardPoll::Card),
+        Some(CardPresence::Collision) => Ok(CardPoll::Collision),
+        Some(CardPresence::NoCard) => Ok(CardPoll::NoCard),
+    }
+}
+
+// Connect to the card in `reader` offering `protocols`, waiting up to `share_timeout` for
+// another process to release it
+fn connect_shared<C: Connector>(
+    connector: &C,
+    reader: &CStr,
+    protocols: Protocols,
+    share_timeout: Duration,
+) -> Result<C::Card, PcscError> {
+    retry_sharing_violation(share_timeout, SHARE_RETRY_INTERVAL, || {
+        connector.connect(reader, ShareMode::Shared, protocols)
+    })
+}
+
+// Send an escape command to `reader` itself over a direct connection, which negotiates no protocol
+fn control_direct<C: Connector>(
+    connector: &C,
+    reader: &CStr,
+    control_code: u32,
+    send: &[u8],
+) -> Result<Vec<u8>, PcscError>
+where
+    C::Card: ApduTransport,
+{
+    connector
+        .connect(reader, ShareMode::Direct, Protocols::UNDEFINED)?
+        .control(control_code, send)
+}
+
+// Retry `connect` while it reports a sharing violation, until `timeout` has passed
+fn retry_sharing_violation<C>(
+    timeout: Duration,
+    interval: Duration,
+    mut connect: impl FnMut() -> Result<C, PcscError>,
+) -> Result<C, PcscError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match connect() {
+            Err(PcscError::SharingViolation) if Instant::now() < deadline => {
+                thread::sleep(interval)
+            }
+            result => return result,
+        }
+    }
+}
+
+// Reader state flags that mean the reader itself has gone away
+fn reader_gone(state: State) -> bool {
+    state.intersects(State::UNKNOWN | State::IGNORE)
+}
+
+/// What a reader reports in its field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardPresence {
+    NoCard,
+    Card,
+    /// More than one card in the field. PC/SC has no dedicated collision signal: readers that
+    /// fail anticollision report a card that is present but mute (`PRESENT | MUTE`), which is
+    /// what this maps. The same flags are set for a single card that does not answer, e.g. one
+    /// held at the edge of the field, so this may also be one unresponsive card.
+    Collision,
+}
+
+// What the reader state reports in the field, or None if the reader has gone away
+fn card_in_slot(state: State) -> Option<CardPresence> {
+    if reader_gone(state) {
+        None
+    } else if !state.contains(State::PRESENT) {
+        Some(CardPresence::NoCard)
+    } else if state.contains(State::MUTE) {
+        Some(CardPresence::Collision)
+    } else {
+        Some(CardPresence::Card)
+    }
+}
+
+/// A change seen by [`Pcsc::poll`], naming the reader it happened on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReaderEvent {
+    CardInserted(String),
+    CardRemoved(String),
+    ReaderAdded(String),
+    ReaderRemoved(String),
+}
+
+// The event a reader's state change amounts to, if any
+fn reader_event(name: &str, previous: State, event: State) -> Option<ReaderEvent> {
+    if reader_gone(event) {
+        return Some(ReaderEvent::ReaderRemoved(name.to_owned()));
+    }
+    match (
+        previous.contains(State::PRESENT),
+        event.contains(State::PRESENT),
+    ) {
+        (false, true) => Some(ReaderEvent::CardInserted(name.to_owned())),
+        (true, false) => Some(ReaderEvent::CardRemoved(name.to_owned())),
+        _ => None,
+    }
+}
+
+// Listed reader names not yet among `known`
+fn added_reader_names(known: &[ReaderState], listed: Vec<CString>) -> Vec<CString> {
+    listed
+        .into_iter()
+        .filter(|name| !known.iter().any(|rs| rs.name() == name.as_c_str()))
+        .collect()
 }
 
 pub struct Pcsc {
     context: Context,
+    // Plug and play notification first, then every reader seen by `poll`
+    poll_state: Vec<ReaderState>,
 }
 
 impl Pcsc {
     pub fn new() -> Result<Self, PcscError> {
         let context = Context::establish(Scope::System)?;
-        Ok(Self { context })
+        Ok(Self {
+            context,
+            poll_state: Vec::new(),
+        })
+    }
+
+    /// Wait up to `timeout` (forever if `None`) for changes on any reader with a single
+    /// status change call covering all readers and reader plug and play. Readers present on
+    /// the first call are reported as added, followed by any cards already in them on the next
+    /// call. An empty list means the timeout passed without changes.
+    pub fn poll(&mut self, timeout: Option<Duration>) -> Result<Vec<ReaderEvent>, PcscError> {
+        if self.poll_state.is_empty() {
+            self.poll_state
+                .push(ReaderState::new(PNP_NOTIFICATION(), State::UNAWARE));
+        }
+        match self
+            .context
+            .get_status_change(timeout, &mut self.poll_state)
+        {
+            Err(PcscError::Timeout) => return Ok(Vec::new()),
+            result => result?,
+        }
+        let mut events = Vec::new();
+        let readers_changed =
+            self.poll_state[0].event_state() != self.poll_state[0].current_state();
+        self.poll_state[0].sync_current_state();
+        self.poll_state.retain_mut(|rs| {
+            if rs.name() == PNP_NOTIFICATION() {
+                return true;
+            }
+            let name = rs.name().to_string_lossy();
+            let event = reader_event(&name, rs.current_state(), rs.event_state());
+            let gone = matches!(event, Some(ReaderEvent::ReaderRemoved(_)));
+            events.extend(event);
+            rs.sync_current_state();
+            !gone
+        });
+        if readers_changed {
+            let listed = match self.context.list_readers_owned() {
+                Err(PcscError::NoReadersAvailable) => Vec::new(),
+                result => result?,
+            };
+            for name in added_reader_names(&self.poll_state, listed) {
+                events.push(ReaderEvent::ReaderAdded(
+                    name.to_string_lossy().into_owned(),
+                ));
+                self.poll_state.push(ReaderState::new(name, State::UNAWARE));
+            }
+        }
+        Ok(events)
+    }
+
+    /// Like [`Pcsc::new`], giving up with [`PcscError::Timeout`] if pcscd has not answered
+    /// within `timeout`, e.g. while it is still starting.
+    ///
+    /// Establishing cannot be interrupted, so it runs on a helper thread that is abandoned on
+    /// expiry; a context it establishes late is released straight away.
+    pub fn new_with_timeout(timeout: Duration) -> Result<Self, PcscError> {
+        let context = with_timeout(timeout, || Context::establish(Scope::System))?;
+        Ok(Self {
+            context,
+            poll_state: Vec::new(),
+        })
+    }
+
+    /// Whether the context is still usable, e.g. false once pcscd has restarted
+    pub fn is_valid(&self) -> bool {
+        self.context.is_valid().is_ok()
+    }
+
+    /// Establish a fresh context, e.g. after pcscd has restarted. Readers obtained before this
+    /// call keep the old context and stop working; fetch them again with [`Pcsc::get_readers`].
+    pub fn reestablish(&mut self) -> Result<(), PcscError> {
+        self.context = Context::establish(Scope::System)?;
+        self.poll_state.clear();
+        Ok(())
+    }
+
+    /// Names of the connected readers, e.g. for display, without setting up [`Reader`]s. No
+    /// readers gives an empty list.
+    pub fn list_reader_names(&mut self) -> Result<Vec<String>, PcscError> {
+        match self.context.list_readers_owned() {
+            Ok(names) => Ok(reader_display_names(names)),
+            Err(PcscError::NoReadersAvailable) => Ok(Vec::new()),
+            Err(error) => Err(error),
+        }
     }
 
     pub fn get_readers(&mut self) -> Result<Vec<Reader>, PcscError> {
-        let mut reader_state = vec![ReaderState::new(PNP_NOTIFICATION(), State::UNAWARE)];
-        self.context.get_status_change(None, &mut reader_state)?;
-        // Ignore readers marked as removed
-        reader_state.retain(|rs| !rs.event_state().intersects(State::UNKNOWN | State::IGNORE));
-        // Return any new readers
-        self.context.list_readers_owned().map(|readers| {
-            readers
-                .iter()
-                .filter_map(|reader_name| {
-                    if !reader_state
-                        .iter()
-                        .any(|rs| rs.name() == reader_name.as_c_str())
-                    {
-                        Some(Reader {
-                            context: self.context.clone(),
-                            state: [ReaderState::new(reader_name.to_owned(), State::UNAWARE)],
-                            is_alive: true,
-                        })
-                    } else {
-                        None
-                    }
-                })
-                .collect()
+        self.get_readers_matching(|_| true)
+    }
+
+    /// Like [`Pcsc::get_readers`], keeping only readers whose name satisfies `predicate`,
+    /// e.g. the PICC slot of a dual-interface reader
+    pub fn get_readers_matching(
+        &mut self,
+        predicate: impl Fn(&str) -> bool,
+    ) -> Result<Vec<Reader>, PcscError> {
+        let names = matching_reader_names(self.context.list_readers_owned()?, predicate);
+        let mut reader_state: Vec<_> = names
+            .into_iter()
+            .map(|name| ReaderState::new(name, State::UNAWARE))
+            .collect();
+        // An UNAWARE query returns the current states immediately
+        if !reader_state.is_empty() {
+            self.context.get_status_change(None, &mut reader_state)?;
+        }
+        let snapshot = reader_state
+            .iter()
+            .map(|rs| (rs.name().to_owned(), rs.event_state()))
+            .collect();
+        let names = retain_listed(snapshot, || match self.context.list_readers_owned() {
+            Err(PcscError::NoReadersAvailable) => Ok(Vec::new()),
+            result => result,
+        })?;
+        Ok(names
+            .into_iter()
+            .map(|reader_name| Reader {
+                context: self.context.clone(),
+                state: [ReaderState::new(reader_name, State::UNAWARE)],
+                is_alive: true,
+                protocols: Protocols::ANY,
+            })
+            .collect())
+    }
+}
+
+// Drop readers a status snapshot flags as gone, unless a fresh listing still has them: a flag
+// can be transient while the reader recovers
+fn retain_listed(
+    snapshot: Vec<(CString, State)>,
+    relist: impl FnOnce() -> Result<Vec<CString>, PcscError>,
+) -> Result<Vec<CString>, PcscError> {
+    if !snapshot.iter().any(|(_, state)| reader_gone(*state)) {
+        return Ok(snapshot.into_iter().map(|(name, _)| name).collect());
+    }
+    let listed = relist()?;
+    Ok(snapshot
+        .into_iter()
+        .filter(|(name, state)| !reader_gone(*state) || listed.contains(name))
+        .map(|(name, _)| name)
+        .collect())
+}
+
+// Reader names as strings, without the plug and play pseudo-reader
+fn reader_display_names(names: Vec<CString>) -> Vec<String> {
+    names
+        .into_iter()
+        .filter(|name| name.as_c_str() != PNP_NOTIFICATION())
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect()
+}
+
+fn matching_reader_names(names: Vec<CString>, predicate: impl Fn(&str) -> bool) -> Vec<CString> {
+    names
+        .into_iter()
+        .filter(|name| predicate(&name.to_string_lossy()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transport::mock::{MockConnector, MockTransport};
+
+    #[test]
+    fn reader_name_filter() {
+        let names = vec![
+            CString::new("ACS ACR1252 Dual Reader [ACR1252 Dual Reader PICC] 00 00").unwrap(),
+            CString::new("ACS ACR1252 Dual Reader [ACR1252 Dual Reader SAM] 00 01").unwrap(),
+        ];
+        let picc = matching_reader_names(names, |name| name.contains("PICC"));
+        assert_eq!(picc.len(), 1);
+        assert!(picc[0].to_str().unwrap().contains("PICC"));
+    }
+
+    #[test]
+    fn poll_events_from_state_transitions() {
+        let name = "ACS ACR122U PICC Interface 00 00";
+        assert_eq!(
+            reader_event(name, State::UNAWARE, State::CHANGED | State::PRESENT),
+            Some(ReaderEvent::CardInserted(name.to_owned()))
+        );
+        assert_eq!(
+            reader_event(name, State::PRESENT, State::CHANGED | State::EMPTY),
+            Some(ReaderEvent::CardRemoved(name.to_owned()))
+        );
+        assert_eq!(
+            reader_event(name, State::EMPTY, State::CHANGED | State::UNKNOWN),
+            Some(ReaderEvent::ReaderRemoved(name.to_owned()))
+        );
+        assert_eq!(
+            reader_event(name, State::PRESENT, State::PRESENT | State::INUSE),
+            None
+        );
+
+        let known = [ReaderState::new(CString::new(name).unwrap(), State::EMPTY)];
+        let listed = vec![
+            CString::new(name).unwrap(),
+            CString::new("SCM Microsystems Inc. SCL3711 reader & NFC device 00 00").unwrap(),
+        ];
+        let added = added_reader_names(&known, listed);
+        assert_eq!(added.len(), 1);
+        assert!(added[0].to_str().unwrap().contains("SCL3711"));
+    }
+
+    #[test]
+    fn atqa_and_sak_from_get_data() {
+        let tag = RfidTag::with_transport(
+            MockTransport::new([vec![0x00, 0x04, 0x90, 0x00], vec![0x08, 0x90, 0x00]]),
+            &[],
+        );
+        assert_eq!(tag.get_atqa().unwrap(), [0x00, 0x04]);
+        assert_eq!(tag.get_sak().unwrap(), 0x08);
+        assert_eq!(
+            tag.card.sent(),
+            [
+                vec![0xFF, 0xCA, 0xF0, 0x00, 0x00],
+                vec![0xFF, 0xCA, 0xF1, 0x00, 0x00]
+            ]
+        );
+    }
+
+    #[test]
+    fn uid_kept_whole_unless_split_requested() {
+        // A 7 byte UID whose last three bytes pass for SAK 08 and ATQA 00 04
+        let reply = vec![0x04, 0x9C, 0x51, 0x2E, 0x08, 0x00, 0x04, 0x90, 0x00];
+        let mut tag = RfidTag::with_transport(MockTransport::new([reply.clone(), reply]), &[]);
+        let whole = tag.read_uid_full().unwrap();
+        assert_eq!(whole.uid.uid, [0x04, 0x9C, 0x51, 0x2E, 0x08, 0x00, 0x04]);
+        assert_eq!((whole.sak, whole.atqa), (None, None));
+
+        tag.set_uid_params(GetDataParams {
+            split_sak_atqa: true,
+            ..Default::default()
+        });
+        let split = tag.read_uid_full().unwrap();
+        assert_eq!(split.uid.uid, [0x04, 0x9C, 0x51, 0x2E]);
+        assert_eq!(split.sak, Some(0x08));
+    }
+
+    #[test]
+    fn uid_params_override() {
+        let mut tag = RfidTag::with_transport(
+            MockTransport::new([
+                vec![0x04, 0xA2, 0x3B, 0x11, 0x90, 0x00],
+                vec![0x04, 0xA2, 0x3B, 0x11, 0x90, 0x00],
+            ]),
+            &[],
+        );
+        tag.uid_info().unwrap();
+        tag.set_uid_params(GetDataParams {
+            p1: 0x00,
+            p2: 0x01,
+            ..Default::default()
+        });
+        assert_eq!(tag.uid_info().unwrap().uid, [0x04, 0xA2, 0x3B, 0x11]);
+        assert_eq!(
+            tag.card.sent(),
+            [
+                vec![0xFF, 0xCA, 0x00, 0x00, 0x00],
+                vec![0xFF, 0xCA, 0x00, 0x01, 0x00]
+            ]
+        );
+    }
+
+    #[test]
+    fn explicit_response_capacity() {
+        let mut reply = vec![0x5A; 32];
+        reply.extend([0x90, 0x00]);
+        let tag = RfidTag::with_transport(MockTransport::new([reply]), &[]);
+        // Le covers a single page, but the reader returns a larger record
+        let response = tag
+            .run_command_with_capacity(PcscCommand::read_binary(0x0004, 0x04), 34)
+            .unwrap();
+        assert_eq!(response.data(), [0x5A; 32]);
+        assert_eq!(tag.card.capacities(), [34]);
+    }
+
+    #[test]
+    fn blocking_call_times_out() {
+        let slow = with_timeout(Duration::from_millis(10), || {
+            thread::sleep(Duration::from_secs(1));
+            Ok(())
+        });
+        assert_eq!(slow, Err(PcscError::Timeout));
+        let quick = with_timeout(Duration::from_secs(1), || {
+            Err::<(), _>(PcscError::NoService)
+        });
+        assert_eq!(quick, Err(PcscError::NoService));
+    }
+
+    #[test]
+    fn ping_removed_card() {
+        let tag = RfidTag::with_transport(MockTransport::new([]), &[]);
+        assert!(tag.ping().unwrap());
+        tag.card.set_status_error(PcscError::RemovedCard);
+        assert!(!tag.ping().unwrap());
+        tag.card.set_status_error(PcscError::NoService);
+        assert_eq!(tag.ping(), Err(PcscError::NoService));
+        assert!(tag.card.sent().is_empty());
+    }
+
+    #[test]
+    fn script_continues_past_failure() {
+        let tag = RfidTag::with_transport(
+            MockTransport::with_results([
+                Err(PcscError::RemovedCard),
+                Ok(vec![0x04, 0xA2, 0x3B, 0x11, 0x90, 0x00]),
+            ]),
+            &[],
+        );
+        let script = [
+            vec![0xFF, 0xB0, 0x00, 0x04, 0x10],
+            vec![0xFF, 0xCA, 0x00, 0x00, 0x00],
+        ];
+        let results = tag.run_script(&script);
+        assert!(matches!(
+            results[0],
+            Err(PcscCodecError::Pcsc(PcscError::RemovedCard))
+        ));
+        let uid = results[1].as_ref().unwrap();
+        assert_eq!(uid.data(), [0x04, 0xA2, 0x3B, 0x11]);
+        assert_eq!(tag.card.sent(), script);
+    }
+
+    #[test]
+    fn reader_names_for_display() {
+        let names = vec![
+            CString::new("ACS ACR122U PICC Interface 00 00").unwrap(),
+            PNP_NOTIFICATION().to_owned(),
+            CString::new("NXP PN533 NFC Controller 00 00").unwrap(),
+        ];
+        assert_eq!(
+            reader_display_names(names),
+            [
+                "ACS ACR122U PICC Interface 00 00",
+                "NXP PN533 NFC Controller 00 00"
+            ]
+        );
+    }
+
+    #[test]
+    fn flapping_reader_is_kept() {
+        let flapping = CString::new("ACS ACR122U PICC Interface 00 00").unwrap();
+        let unplugged = CString::new("NXP PN533 NFC Controller 00 00").unwrap();
+        let steady = CString::new("HID Global OMNIKEY 5022 Smart Card Reader 00 00").unwrap();
+        let snapshot = vec![
+            (flapping.clone(), State::CHANGED | State::UNKNOWN),
+            (unplugged.clone(), State::CHANGED | State::UNKNOWN),
+            (steady.clone(), State::CHANGED | State::EMPTY),
+        ];
+        let names = retain_listed(snapshot, || Ok(vec![flapping.clone(), steady.clone()])).unwrap();
+        assert_eq!(names, vec![flapping, steady.clone()]);
+
+        // Nothing flagged, so no second listing is needed
+        let names = retain_listed(vec![(steady.clone(), State::EMPTY)], || {
+            panic!("relisted without a flagged reader")
         })
+        .unwrap();
+        assert_eq!(names, vec![steady]);
+    }
+
+    #[test]
+    fn state_change_outcomes() {
+        let tag = || Ok(RfidTag::with_transport(MockTransport::new([]), &[]));
+        let no_connect = || -> Result<RfidTag<MockTransport>, PcscError> {
+            panic!("connected without a single new card")
+        };
+        assert!(matches!(
+            state_change(State::EMPTY, State::CHANGED | State::PRESENT, tag),
+            Ok(CardPoll::Card(_))
+        ));
+        assert!(matches!(
+            state_change(
+                State::EMPTY,
+                State::CHANGED | State::PRESENT | State::MUTE,
+                no_connect
+            ),
+            Ok(CardPoll::Collision)
+        ));
+        assert!(matches!(
+            state_change(State::PRESENT, State::CHANGED | State::EMPTY, no_connect),
+            Ok(CardPoll::NoCard)
+        ));
+        assert!(matches!(
+            state_change(State::PRESENT, State::PRESENT, no_connect),
+            Ok(CardPoll::NoCard)
+        ));
+        assert!(matches!(
+            state_change(State::PRESENT, State::CHANGED | State::UNKNOWN, no_connect),
+            Ok(CardPoll::ReaderGone)
+        ));
+        assert!(matches!(
+            state_change::<MockTransport>(State::EMPTY, State::CHANGED | State::PRESENT, || {
+                Err(PcscError::SharingViolation)
+            }),
+            Err(PcscError::SharingViolation)
+        ));
+    }
+
+    #[test]
+    fn shared_connect_offers_protocols() {
+        let connector = MockConnector::new([Arc::new(MockTransport::new([]))]);
+        let reader = c"ACS ACR122U PICC Interface 00 00";
+        connect_shared(&connector, reader, Protocols::T1, Duration::ZERO).unwrap();
+        assert_eq!(
+            connector.connections(),
+            [(reader.to_owned(), ShareMode::Shared, Protocols::T1)]
+        );
+    }
+
+    #[test]
+    fn control_direct_connects_directly() {
+        let card = Arc::new(MockTransport::new([vec![0x01, 0x02]]));
+        let connector = MockConnector::new([card.clone()]);
+        let reader = c"ACS ACR122U PICC Interface 00 00";
+        let code = 0x0031_0000 + 3500 * 4;
+        assert_eq!(
+            control_direct(&connector, reader, code, &[0xE0, 0x00, 0x00, 0x18, 0x00]).unwrap(),
+            [0x01, 0x02]
+        );
+        assert_eq!(
+            connector.connections(),
+            [(reader.to_owned(), ShareMode::Direct, Protocols::UNDEFINED)]
+        );
+        assert_eq!(
+            card.controls(),
+            [(code, vec![0xE0, 0x00, 0x00, 0x18, 0x00])]
+        );
+    }
+
+    #[test]
+    fn card_iter_ends_with_reader() {
+        let tag = || RfidTag::with_transport(MockTransport::new([]), &[]);
+        let mut polls = vec![
+            Ok(CardPoll::Card(tag())),
+            Ok(CardPoll::NoCard),
+            Err(PcscError::ResetCard),
+            Ok(CardPoll::Card(tag())),
+            Ok(CardPoll::ReaderGone),
+        ]
+        .into_iter();
+        let mut iter = cards(move || polls.next().expect("polled after the reader was gone"));
+        assert!(matches!(iter.next(), Some(Ok(_))));
+        assert!(matches!(iter.next(), Some(Err(PcscError::ResetCard))));
+        assert!(matches!(iter.next(), Some(Ok(_))));
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn card_presence_from_state() {
+        assert_eq!(
+            card_in_slot(State::CHANGED | State::PRESENT),
+            Some(CardPresence::Card)
+        );
+        assert_eq!(
+            card_in_slot(State::PRESENT | State::INUSE | State::EXCLUSIVE),
+            Some(CardPresence::Card)
+        );
+        assert_eq!(
+            card_in_slot(State::CHANGED | State::PRESENT | State::MUTE),
+            Some(CardPresence::Collision)
+        );
+        assert_eq!(
+            card_in_slot(State::CHANGED | State::EMPTY),
+            Some(CardPresence::NoCard)
+        );
+        assert_eq!(card_in_slot(State::CHANGED | State::UNKNOWN), None);
+        assert_eq!(card_in_slot(State::IGNORE), None);
+    }
+
+    #[test]
+    fn read_upper_4k_sector() {
+        let mut replies = vec![vec![0x90, 0x00]];
+        for block in 0..16 {
+            let mut reply = vec![block; 16];
+            reply.extend([0x90, 0x00]);
+            replies.push(reply);
+        }
+        let tag = RfidTag::with_transport(MockTransport::new(replies), &[]);
+        let blocks = tag.read_sector(32, KeyType::MifareA, 0).unwrap();
+        assert_eq!(blocks.len(), 16);
+        assert_eq!(blocks[15], [15; 16]);
+        let sent = tag.card.sent();
+        assert_eq!(
+            sent[0],
+            vec![0xFF, 0x86, 0x00, 0x00, 0x05, 0x01, 0x00, 0x80, 0x60, 0x00]
+        );
+        assert_eq!(sent[1], vec![0xFF, 0xB0, 0x00, 0x80, 0x10]);
+        assert_eq!(sent[16], vec![0xFF, 0xB0, 0x00, 0x8F, 0x10]);
+    }
+
+    #[test]
+    fn dump_skips_locked_sectors() {
+        let ok = vec![0x90, 0x00];
+        let denied = vec![0x63, 0x00];
+        // Sector 0 opens with key A, sectors 1-4 refuse both key types
+        let mut replies = vec![ok.clone(), ok.clone()];
+        for block in 0..4 {
+            let mut reply = vec![block; 16];
+            reply.extend(&ok);
+            replies.push(reply);
+        }
+        for _ in 1..5 {
+            replies.extend([ok.clone(), denied.clone(), denied.clone()]);
+        }
+        let atr = atr::AtrBuilder::new(Standard::Iso14443APart3, CardName::MifareMini).build();
+        let tag = RfidTag::with_transport(MockTransport::new(replies), &atr);
+        let blocks = tag.dump(&[[0xFF; 6]]).unwrap();
+        assert_eq!(
+            blocks.iter().map(|(block, _)| *block).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+        assert_eq!(blocks[3].1, vec![3; 16]);
+        assert_eq!(tag.card.sent().len(), 18);
+    }
+
+    #[test]
+    fn get_response_chaining() {
+        let transport =
+            MockTransport::new([vec![0x01, 0x02, 0x61, 0x02], vec![0x03, 0x04, 0x90, 0x00]]);
+        let tag = RfidTag::with_transport(transport, &[]);
+        let response = tag
+            .run_command_full(PcscCommand::read_binary(4, 0))
+            .unwrap();
+        assert_eq!(response.data(), &[0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(response.sw(), PcscStatusWords::Success);
+        assert_eq!(tag.card.sent()[1], vec![0x00, 0xC0, 0x00, 0x00, 0x02]);
+    }
+
+    #[test]
+    fn get_response_chaining_capped() {
+        let tag = RfidTag::with_transport(
+            MockTransport::new(std::iter::repeat_n(vec![0x00, 0x61, 0x01], 258)),
+            &[],
+        );
+        assert!(matches!(
+            tag.run_command_full(PcscCommand::read_binary(4, 0)),
+            Err(PcscCodecError::TooLong)
+        ));
+        assert_eq!(tag.card.sent().len(), 1 + MAX_GET_RESPONSE_ROUNDS);
+    }
+
+    #[test]
+    fn wrong_le_retry() {
+        let transport =
+            MockTransport::new([vec![0x6C, 0x04], vec![0x01, 0x02, 0x03, 0x04, 0x90, 0x00]]);
+        let tag = RfidTag::with_transport(transport, &[]);
+        let response = tag
+            .run_command_full(PcscCommand::read_binary(4, 0x10))
+            .unwrap();
+        assert_eq!(response.data(), &[0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(
+            tag.card.sent(),
+            vec![
+                vec![0xFF, 0xB0, 0x00, 0x04, 0x10],
+                vec![0xFF, 0xB0, 0x00, 0x04, 0x04]
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_le_retry_extended() {
+        let mut reply = vec![0xA5; 0x10];
+        reply.extend([0x90, 0x00]);
+        let tag = RfidTag::with_transport(MockTransport::new([vec![0x6C, 0x10], reply]), &[]);
+        let response = tag
+            .run_command_full(PcscCommand::read_binary_extended(0, 512))
+            .unwrap();
+        assert_eq!(response.data().len(), 0x10);
+        assert_eq!(
+            tag.card.sent(),
+            vec![
+                vec![0xFF, 0xB0, 0x00, 0x00, 0x00, 0x02, 0x00],
+                vec![0xFF, 0xB0, 0x00, 0x00, 0x00, 0x00, 0x10]
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_le_without_le_not_retried() {
+        let tag = RfidTag::with_transport(MockTransport::new([vec![0x6C, 0x04]]), &[]);
+        let response = tag
+            .run_command_full(PcscCommand::update_binary(4, vec![1, 2, 3, 4]))
+            .unwrap();
+        assert_eq!(response.sw(), PcscStatusWords::WrongLengthLe(0x04));
+        assert_eq!(
+            tag.card.sent(),
+            vec![vec![0xFF, 0xD6, 0x00, 0x04, 0x04, 1, 2, 3, 4]]
+        );
+    }
+
+    #[test]
+    fn wrong_le_retried_once() {
+        let transport = MockTransport::new([vec![0x6C, 0x04], vec![0x6C, 0x04]]);
+        let tag = RfidTag::with_transport(transport, &[]);
+        let response = tag
+            .run_command_full(PcscCommand::read_binary(4, 0x10))
+            .unwrap();
+        assert_eq!(response.sw(), PcscStatusWords::WrongLengthLe(0x04));
+        assert_eq!(tag.card.sent().len(), 2);
+    }
+
+    #[test]
+    fn retry_transient_errors() {
+        let tag = RfidTag::with_transport(
+            MockTransport::with_results([
+                Err(PcscError::ResetCard),
+                Err(PcscError::UnresponsiveCard),
+                Ok(vec![0x04, 0xA2, 0x3B, 0x11, 0x90, 0x00]),
+            ]),
+            &[],
+        );
+        let response = tag
+            .run_command_retry(PcscCommand::get_data(GetDataType::Uid), 2)
+            .unwrap();
+        assert_eq!(response.data(), [0x04, 0xA2, 0x3B, 0x11]);
+        assert_eq!(tag.card.sent().len(), 3);
+
+        let tag = RfidTag::with_transport(
+            MockTransport::with_results([Err(PcscError::NoSmartcard)]),
+            &[],
+        );
+        assert!(matches!(
+            tag.run_command_retry(PcscCommand::get_data(GetDataType::Uid), 3),
+            Err(PcscCodecError::Pcsc(PcscError::NoSmartcard))
+        ));
+        assert_eq!(tag.card.sent().len(), 1);
+    }
+
+    #[test]
+    fn select_missing_application() {
+        let tag = RfidTag::with_transport(MockTransport::new([vec![0x6A, 0x82]]), &[]);
+        assert!(matches!(
+            tag.select_application(&[0xA0, 0x00, 0x00, 0x00, 0x03, 0x10, 0x10]),
+            Err(PcscCodecError::StatusError {
+                sw: PcscStatusWords::CommandError(0x82),
+                info: Some(command::PcscErrorCodeInfo::FileNotFound),
+            })
+        ));
+        assert_eq!(tag.card.sent()[0][..5], [0x00, 0xA4, 0x04, 0x00, 0x07]);
+    }
+
+    #[test]
+    fn firmware_version() {
+        let tag = RfidTag::with_transport(MockTransport::new([b"ACR122U207".to_vec()]), &[]);
+        assert_eq!(tag.reader_firmware_version().unwrap(), "ACR122U207");
+        assert_eq!(tag.card.sent(), [vec![0xFF, 0x00, 0x48, 0x00, 0x00]]);
+
+        let tag = RfidTag::with_transport(MockTransport::new([vec![0x6A, 0x81]]), &[]);
+        assert!(matches!(
+            tag.reader_firmware_version(),
+            Err(PcscCodecError::Pcsc(PcscError::UnsupportedFeature))
+        ));
+
+        let mut tag = RfidTag::with_transport(MockTransport::new([]), &[]);
+        tag.reader_model = ReaderModel::Omnikey;
+        assert!(matches!(
+            tag.reader_firmware_version(),
+            Err(PcscCodecError::UnsupportedReader(ReaderModel::Omnikey))
+        ));
+        assert!(tag.card.sent().is_empty());
+    }
+
+    #[test]
+    fn transparent_session_refused_by_reader() {
+        let mut tag = RfidTag::with_transport(MockTransport::new([]), &[]);
+        tag.reader_model = ReaderModel::Acr122u;
+        let refused = |result: Result<(), PcscCodecError>| {
+            matches!(
+                result,
+                Err(PcscCodecError::UnsupportedReader(ReaderModel::Acr122u))
+            )
+        };
+        assert!(refused(tag.start_transparent_session()));
+        assert!(refused(tag.end_transparent_session()));
+        assert!(refused(tag.transceive(&[0x30, 0x04]).map(drop)));
+        assert!(refused(tag.switch_protocol(0x03, 0x00)));
+        #[cfg(feature = "ultralight-c")]
+        assert!(refused(tag.authenticate_ultralight_c([0; 16])));
+        assert!(tag.card.sent().is_empty());
+    }
+
+    #[test]
+    fn read_range_chunks() {
+        let chunk = |fill: u8| {
+            let mut reply = vec![fill; 16];
+            reply.extend([0x90, 0x00]);
+            reply
+        };
+        let tag = RfidTag::with_transport(MockTransport::new([chunk(1), chunk(2), chunk(3)]), &[]);
+        let data = tag.read_range(4, 40).unwrap();
+        assert_eq!(data.len(), 40);
+        assert_eq!(data[..16], [1; 16]);
+        assert_eq!(data[32..], [3; 8]);
+        let sent = tag.card.sent();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[2], [0xFF, 0xB0, 0x00, 0x06, 0x10]);
+
+        let tag = RfidTag::with_transport(MockTransport::new([chunk(1), vec![0x69, 0x82]]), &[]);
+        assert!(matches!(
+            tag.read_range(4, 48),
+            Err(PcscCodecError::StatusError { .. })
+        ));
+        assert_eq!(tag.card.sent().len(), 2);
+    }
+
+    #[test]
+    fn read_range_four_byte_blocks() {
+        let block = |fill: u8| vec![fill, fill, fill, fill, 0x90, 0x00];
+        let tag = RfidTag::with_transport(MockTransport::new([block(1), block(2), block(3)]), &[]);
+        let data = tag.read_range_in_blocks(8, 10, 4).unwrap();
+        assert_eq!(data, [1, 1, 1, 1, 2, 2, 2, 2, 3, 3]);
+        let sent = tag.card.sent();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[0], [0xFF, 0xB0, 0x00, 0x08, 0x04]);
+        assert_eq!(sent[2], [0xFF, 0xB0, 0x00, 0x0A, 0x04]);
+        assert_eq!(tag.card.capacities(), [6, 6, 6]);
+    }
+
+    #[test]
+    fn unclassified_atr_still_usable() {
+        let uid = || vec![0x04, 0x52, 0x1C, 0x9A, 0x2F, 0x3E, 0x80, 0x90, 0x00];
+        // MIFARE DESFire EV1
+        let tag = RfidTag::with_transport(
+            MockTransport::new([uid()]),
+            &[0x3B, 0x81, 0x80, 0x01, 0x80, 0x80],
+        );
+        assert!(tag.is_recognized());
+        assert_eq!(tag.uid_info().unwrap().uid.len(), 7);
+        // Storage card ATR cut off inside the card name, which used to panic
+        let tag = RfidTag::with_transport(
+            MockTransport::new([uid()]),
+            &[
+                0x3B, 0x8F, 0x80, 0x01, 0x80, 0x4F, 0x0C, 0xA0, 0x00, 0x00, 0x03, 0x06, 0x03, 0x00,
+            ],
+        );
+        assert_eq!(tag.card_name(), None);
+        assert_eq!(tag.uid_info().unwrap().uid.len(), 7);
+        // Nothing recognizable at all
+        let tag = RfidTag::with_transport(MockTransport::new([uid()]), &[0x3B, 0x00]);
+        assert!(!tag.is_recognized());
+        assert!(tag.uid_info().is_ok());
+    }
+
+    #[test]
+    fn transparent_session() {
+        let tag = RfidTag::with_transport(
+            MockTransport::new([
+                vec![0xC0, 0x03, 0x00, 0x90, 0x00, 0x90, 0x00],
+                vec![0xC0, 0x03, 0x01, 0x6A, 0x81, 0x90, 0x00],
+            ]),
+            &[],
+        );
+        tag.start_transparent_session().unwrap();
+        assert!(matches!(
+            tag.end_transparent_session(),
+            Err(PcscCodecError::StatusError {
+                sw: PcscStatusWords::CommandError(0x81),
+                info: Some(command::PcscErrorCodeInfo::FunctionNotSupported),
+            })
+        ));
+        assert_eq!(
+            tag.card.sent(),
+            [
+                vec![0xFF, 0xC2, 0x00, 0x00, 0x02, 0x81, 0x00],
+                vec![0xFF, 0xC2, 0x00, 0x00, 0x02, 0x82, 0x00],
+            ]
+        );
+        assert_eq!(
+            tag.card.capacities(),
+            [PcscResponse::MAX_LENGTH, PcscResponse::MAX_LENGTH]
+        );
+    }
+
+    #[test]
+    fn tag_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<RfidTag>();
+
+        let tag = RfidTag::with_transport(
+            MockTransport::new([vec![0x04, 0xA2, 0x3B, 0x11, 0x90, 0x00]]),
+            &[],
+        );
+        let uid = thread::spawn(move || tag.uid_info().map(|info| info.uid))
+            .join()
+            .unwrap()
+            .unwrap();
+        assert_eq!(uid, [0x04, 0xA2, 0x3B, 0x11]);
+    }
+
+    #[test]
+    fn response_buffer_sizing() {
+        assert_eq!(response_buffer_len(18, None), 18);
+        assert_eq!(
+            response_buffer_len(PcscResponse::MAX_LENGTH, None),
+            PcscResponse::MAX_LENGTH
+        );
+        // A reader limited to 64 byte messages never needs a full short APDU buffer
+        assert_eq!(response_buffer_len(PcscResponse::MAX_LENGTH, Some(64)), 64);
+        assert_eq!(response_buffer_len(18, Some(271)), 18);
+        assert_eq!(response_buffer_len(18, Some(0)), 2);
+
+        let mut tag = RfidTag::with_transport(MockTransport::new([vec![0x90, 0x00]]), &[]);
+        tag.max_response_len = Some(64);
+        tag.run_command(PcscCommand::get_data(GetDataType::Uid))
+            .unwrap();
+        let capacity = tag.card.capacities()[0];
+        assert!((64..PcscResponse::MAX_LENGTH).contains(&capacity));
+    }
+
+    #[test]
+    fn provision_plan() {
+        let ok = || vec![0x90, 0x00];
+        let plan = [
+            (4, [0xFF; 6], KeyType::MifareA, vec![0x11; 16]),
+            (
+                8,
+                [0xA0, 0xA1, 0xA2, 0xA3, 0xA4, 0xA5],
+                KeyType::MifareB,
+                vec![0x22; 16],
+            ),
+        ];
+        let tag = RfidTag::with_transport(MockTransport::new(std::iter::repeat_n(ok(), 6)), &[]);
+        tag.provision(&plan).unwrap();
+        let sent = tag.card.sent();
+        assert_eq!(sent.len(), 6);
+        assert_eq!(sent[3][..5], [0xFF, 0x82, 0x00, 0x00, 0x06]);
+        assert_eq!(
+            sent[4],
+            [0xFF, 0x86, 0x00, 0x00, 0x05, 0x01, 0x00, 0x08, 0x61, 0x00]
+        );
+        assert_eq!(sent[5][..5], [0xFF, 0xD6, 0x00, 0x08, 0x10]);
+
+        let tag = RfidTag::with_transport(
+            MockTransport::new([ok(), ok(), ok(), ok(), vec![0x69, 0x82]]),
+            &[],
+        );
+        let error = tag.provision(&plan).unwrap_err();
+        assert!(matches!(error, PcscCodecError::Block { block: 8, .. }));
+        assert_eq!(tag.card.sent().len(), 5);
+    }
+
+    #[test]
+    fn sharing_violation_retried() {
+        let mut attempts = 0;
+        let connected = retry_sharing_violation(Duration::from_secs(5), Duration::ZERO, || {
+            attempts += 1;
+            if attempts < 3 {
+                Err(PcscError::SharingViolation)
+            } else {
+                Ok("card")
+            }
+        });
+        assert_eq!(connected, Ok("card"));
+        assert_eq!(attempts, 3);
+
+        // Without a timeout the first violation is returned
+        let mut attempts = 0;
+        let connected: Result<(), _> =
+            retry_sharing_violation(Duration::ZERO, Duration::ZERO, || {
+                attempts += 1;
+                Err(PcscError::SharingViolation)
+            });
+        assert_eq!(connected, Err(PcscError::SharingViolation));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn warm_reset_reparses_atr() {
+        let atr =
+            atr::AtrBuilder::new(Standard::Iso14443APart3, CardName::MifareStandard4K).build();
+        let mut tag = RfidTag::with_transport(MockTransport::new([atr.clone()]), &[0x3B, 0x00]);
+        assert_eq!(tag.warm_reset().unwrap(), atr);
+        assert_eq!(tag.card.dispositions(), [Disposition::ResetCard]);
+        assert_eq!(tag.card_name(), Some(CardName::MifareStandard4K));
+
+        let _in_flight = Arc::clone(&tag.card);
+        assert_eq!(tag.warm_reset(), Err(PcscError::SharingViolation));
+    }
+
+    #[test]
+    fn reparse_late_atr() {
+        let mut tag = RfidTag::with_transport(MockTransport::new([]), &[0x3B, 0x00]);
+        assert!(!tag.is_recognized());
+        tag.reparse_atr(&[
+            0x3B, 0x8F, 0x80, 0x01, 0x80, 0x4F, 0x0C, 0xA0, 0x00, 0x00, 0x03, 0x06, 0x03, 0x00,
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x6A,
+        ]);
+        assert_eq!(tag.tag_type(), Some(TagType::StorageCard));
+        assert_eq!(tag.standard(), Some(Standard::Iso14443APart3));
+        assert_eq!(tag.card_name(), Some(CardName::MifareStandard1K));
+        assert!(tag.card.sent().is_empty());
+    }
+
+    #[cfg(feature = "ultralight-c")]
+    #[test]
+    fn ultralight_c_authentication() {
+        use des::cipher::generic_array::GenericArray;
+        use des::cipher::{BlockEncrypt, KeyInit};
+
+        let key = *b"IEMKAERB!NACUOYF";
+        let cipher = des::TdesEde2::new(GenericArray::from_slice(&key));
+        let encrypt = |mut block: [u8; 8], iv: [u8; 8]| {
+            block.iter_mut().zip(iv).for_each(|(b, i)| *b ^= i);
+            cipher.encrypt_block(GenericArray::from_mut_slice(&mut block));
+            block
+        };
+        let rnd_a = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        let rnd_b = [0xA1, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7, 0xA8];
+        let ek_rnd_b = encrypt(rnd_b, [0; 8]);
+        let (token, iv) = ultralight::challenge_response(&key, ek_rnd_b, rnd_a);
+        let ek_rnd_a = encrypt([0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x01], iv);
+
+        let session_ok = || vec![0xC0, 0x03, 0x00, 0x90, 0x00, 0x90, 0x00];
+        let icc = |code: u8, block: [u8; 8]| {
+            let mut reply = vec![0xC0, 0x03, 0x00, 0x90, 0x00, 0x97, 0x09, code];
+            reply.extend(block);
+            reply.extend([0x90, 0x00]);
+            reply
+        };
+        let tag = RfidTag::with_transport(
+            MockTransport::new([
+                session_ok(),
+                icc(0xAF, ek_rnd_b),
+                icc(0x00, ek_rnd_a),
+                session_ok(),
+            ]),
+            &[],
+        );
+        tag.authenticate_ultralight_c_with(&key, rnd_a).unwrap();
+        let sent = tag.card.sent();
+        assert_eq!(
+            sent[1],
+            [0xFF, 0xC2, 0x00, 0x01, 0x04, 0x95, 0x02, 0x1A, 0x00]
+        );
+        assert_eq!(
+            sent[2][..8],
+            [0xFF, 0xC2, 0x00, 0x01, 0x13, 0x95, 0x11, 0xAF]
+        );
+        assert_eq!(sent[2][8..], token);
+        assert_eq!(sent[3], [0xFF, 0xC2, 0x00, 0x00, 0x02, 0x82, 0x00]);
+
+        // A tag answering with the wrong key's RndA' is rejected, and the session still closed
+        let tag = RfidTag::with_transport(
+            MockTransport::new([
+                session_ok(),
+                icc(0xAF, ek_rnd_b),
+                icc(0x00, [0; 8]),
+                session_ok(),
+            ]),
+            &[],
+        );
+        assert!(matches!(
+            tag.authenticate_ultralight_c_with(&key, rnd_a),
+            Err(PcscCodecError::AuthenticationFailed)
+        ));
+        assert_eq!(tag.card.sent().len(), 4);
+    }
+
+    #[test]
+    fn display_summary() {
+        let atr = [
+            0x3B, 0x8F, 0x80, 0x01, 0x80, 0x4F, 0x0C, 0xA0, 0x00, 0x00, 0x03, 0x06, 0x03, 0x00,
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x6A,
+        ];
+        let tag = RfidTag::with_transport(MockTransport::new([]), &atr);
+        assert_eq!(
+            tag.to_string(),
+            "StorageCard Iso14443APart3 MifareStandard1K \
+             [3B 8F 80 01 80 4F 0C A0 00 00 03 06 03 00 01 00 00 00 00 6A]"
+        );
+        assert!(tag.card.sent().is_empty());
+    }
+
+    #[test]
+    fn write_then_read_ndef() {
+        // NTAG213-like: 144 byte data area from page 4, read/write access
+        let cc = [0xE1, 0x10, 0x12, 0x00];
+        let records = vec![
+            NdefRecord::Uri("https://www.example.com/nfc".to_string()),
+            NdefRecord::Text {
+                language: "en".to_string(),
+                text: "hello from the tag".to_string(),
+            },
+        ];
+        let mut replies = vec![[&cc[..], &[0; 12], &[0x90, 0x00]].concat()];
+        replies.extend(std::iter::repeat_n(vec![0x90, 0x00], 36));
+        let tag = RfidTag::with_transport(MockTransport::new(replies), &[]);
+        tag.write_ndef(&records).unwrap();
+
+        // Rebuild the tag memory from the Update Binary commands sent
+        let mut memory = [0; 4 * 45];
+        memory[12..16].copy_from_slice(&cc);
+        let writes = tag.card.sent()[1..].to_vec();
+        assert!(writes.len() > 4);
+        for (page, apdu) in (4..).zip(&writes) {
+            assert_eq!(apdu[..5], [0xFF, 0xD6, 0x00, page, 0x04]);
+            memory[page as usize * 4..page as usize * 4 + 4].copy_from_slice(&apdu[5..]);
+        }
+
+        let read = |page: usize| [&memory[page * 4..page * 4 + 16], &[0x90, 0x00]].concat();
+        let replies = (3..)
+            .step_by(4)
+            .take(writes.len().div_ceil(4) + 1)
+            .map(read);
+        let tag = RfidTag::with_transport(MockTransport::new(replies), &[]);
+        assert_eq!(tag.read_ndef().unwrap(), records);
+        let pages: Vec<u8> = tag.card.sent().iter().map(|apdu| apdu[3]).collect();
+        assert_eq!(pages[..3], [3, 7, 11]);
+    }
+
+    #[test]
+    fn write_ndef_refuses_read_only_tag() {
+        let reply = [&[0xE1, 0x10, 0x12, 0x0F][..], &[0; 12], &[0x90, 0x00]].concat();
+        let tag = RfidTag::with_transport(MockTransport::new([reply]), &[]);
+        assert!(matches!(
+            tag.write_ndef(&[NdefRecord::Uri("https://example.com".to_string())]),
+            Err(NdefError::ReadOnly)
+        ));
+        assert_eq!(tag.card.sent().len(), 1);
+    }
+
+    #[test]
+    fn read_ndef_type4_exchange() {
+        let tag = RfidTag::with_transport(
+            MockTransport::new([
+                vec![0x90, 0x00],
+                vec![0x90, 0x00],
+                vec![
+                    0x00, 0x0F, 0x20, 0x00, 0x3B, 0x00, 0x34, 0x04, 0x06, 0xE1, 0x04, 0x00, 0x80,
+                    0x00, 0x00, 0x90, 0x00,
+                ],
+                vec![0x90, 0x00],
+                vec![0x00, 0x0C, 0x90, 0x00],
+                vec![
+                    0xD1, 0x01, 0x08, 0x54, 0x02, 0x65, 0x6E, 0x68, 0x65, 0x6C, 0x6C, 0x6F, 0x90,
+                    0x00,
+                ],
+            ]),
+            &[],
+        );
+        assert_eq!(
+            tag.read_ndef_type4().unwrap(),
+            [NdefRecord::Text {
+                language: "en".into(),
+                text: "hello".into(),
+            }]
+        );
+        let sent = tag.card.sent();
+        assert_eq!(
+            sent[0][..12],
+            [0x00, 0xA4, 0x04, 0x00, 0x07, 0xD2, 0x76, 0x00, 0x00, 0x85, 0x01, 0x01]
+        );
+        assert_eq!(sent[1][..7], [0x00, 0xA4, 0x00, 0x0C, 0x02, 0xE1, 0x03]);
+        assert_eq!(sent[2], [0x00, 0xB0, 0x00, 0x00, 0x0F]);
+        assert_eq!(sent[3][..7], [0x00, 0xA4, 0x00, 0x0C, 0x02, 0xE1, 0x04]);
+        assert_eq!(sent[4], [0x00, 0xB0, 0x00, 0x00, 0x02]);
+        assert_eq!(sent[5], [0x00, 0xB0, 0x00, 0x02, 0x0C]);
+    }
+
+    #[test]
+    fn read_ndef_type4_nlen_out_of_range() {
+        let cc = |max_size: [u8; 2]| {
+            vec![
+                0x00,
+                0x0F,
+                0x20,
+                0x00,
+                0x3B,
+                0x00,
+                0x34,
+                0x04,
+                0x06,
+                0xE1,
+                0x04,
+                max_size[0],
+                max_size[1],
+                0x00,
+                0x00,
+                0x90,
+                0x00,
+            ]
+        };
+        for (max_size, nlen) in [([0x00, 0x80], [0x00, 0x7F]), ([0xFF, 0xFF], [0xFF, 0xFE])] {
+            let tag = RfidTag::with_transport(
+                MockTransport::new([
+                    vec![0x90, 0x00],
+                    vec![0x90, 0x00],
+                    cc(max_size),
+                    vec![0x90, 0x00],
+                    vec![nlen[0], nlen[1], 0x90, 0x00],
+                ]),
+                &[],
+            );
+            assert!(matches!(
+                tag.read_ndef_type4(),
+                Err(NdefError::CapacityExceeded)
+            ));
+            assert_eq!(tag.card.sent().len(), 5);
+        }
+    }
+
+    #[test]
+    fn read_ndef_type4_empty_file() {
+        let tag = RfidTag::with_transport(
+            MockTransport::new([
+                vec![0x90, 0x00],
+                vec![0x90, 0x00],
+                vec![
+                    0x00, 0x0F, 0x20, 0x00, 0x3B, 0x00, 0x34, 0x04, 0x06, 0xE1, 0x04, 0x00, 0x80,
+                    0x00, 0x00, 0x90, 0x00,
+                ],
+                vec![0x90, 0x00],
+                vec![0x00, 0x00, 0x90, 0x00],
+            ]),
+            &[],
+        );
+        assert!(matches!(tag.read_ndef_type4(), Err(NdefError::NoMessage)));
+    }
+
+    #[test]
+    fn read_binary_le_zero_full_response() {
+        let mut reply = vec![0x5A; 256];
+        reply.extend([0x90, 0x00]);
+        let tag = RfidTag::with_transport(MockTransport::new([reply]), &[]);
+        let response = tag
+            .run_command_checked(PcscCommand::read_binary(0, 0))
+            .unwrap();
+        assert_eq!(response.data().len(), 256);
+        assert_eq!(tag.card.capacities(), [258]);
+    }
+
+    #[test]
+    fn read_binary_extended_response() {
+        let mut reply = vec![0xA5; 512];
+        reply.extend([0x90, 0x00]);
+        let tag = RfidTag::with_transport(MockTransport::new([reply]), &[]);
+        let response = tag
+            .run_command_checked(PcscCommand::read_binary_extended(0, 512))
+            .unwrap();
+        assert_eq!(response.data().len(), 512);
+        assert_eq!(tag.card.capacities(), [514]);
+    }
+
+    #[test]
+    fn same_card_compares_uid() {
+        let tag = |uid: &[u8]| {
+            let mut reply = uid.to_vec();
+            reply.extend([0x90, 0x00]);
+            RfidTag::with_transport(MockTransport::new([reply]), &[])
+        };
+        let uid = [0x04, 0xA2, 0x3B, 0x11, 0x52, 0x80, 0x00];
+        assert!(tag(&uid).same_card(&tag(&uid)).unwrap());
+        assert!(!tag(&uid)
+            .same_card(&tag(&[0x04, 0xA2, 0x3B, 0x12]))
+            .unwrap());
+    }
+
+    #[test]
+    fn attribute_pass_through() {
+        let tag = RfidTag::with_transport(MockTransport::new([]), &[]);
+        assert!(matches!(
+            tag.get_attribute(Attribute::MaxIfsd),
+            Err(PcscError::UnsupportedFeature)
+        ));
+        tag.set_attribute(Attribute::MaxIfsd, &[0xFE, 0x00, 0x00, 0x00])
+            .unwrap();
+        assert_eq!(
+            tag.get_attribute(Attribute::MaxIfsd).unwrap(),
+            [0xFE, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn send_apdu_checked_short_response() {
+        let tag = RfidTag::with_transport(MockTransport::new([vec![0x6F], vec![0x90, 0x00]]), &[]);
+        let apdu = [0xFF, 0xCA, 0x00, 0x00, 0x00];
+        let error = tag.send_apdu_checked(&apdu, 2).unwrap_err();
+        assert!(matches!(
+            error,
+            PcscCodecError::ResponseTooShort { len: 1, min_len: 2 }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Response of 1 bytes, expected at least 2"
+        );
+        assert_eq!(tag.send_apdu_checked(&apdu, 2).unwrap(), [0x90, 0x00]);
+    }
+
+    #[test]
+    fn read_page_window() {
+        let mut reply: Vec<u8> = (0..16).collect();
+        reply.extend([0x90, 0x00]);
+        let tag = RfidTag::with_transport(MockTransport::new([reply, vec![0x63, 0x00]]), &[]);
+        let window = tag.read_page(4).unwrap();
+        assert_eq!(window[15], 15);
+        assert!(matches!(
+            tag.read_page(8),
+            Err(PcscCodecError::StatusError {
+                sw: PcscStatusWords::AllowedRetries(0),
+                info: None,
+            })
+        ));
     }
 }
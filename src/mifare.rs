@@ -0,0 +1,203 @@
+//! Sector layout and access condition helpers for MIFARE Classic cards
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("Access bits do not match their inverted copy")]
+pub struct InvalidAccessBits;
+
+/// Which key grants an operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRequirement {
+    Never,
+    KeyA,
+    KeyB,
+    KeyAOrB,
+}
+
+/// First block and block count of a MIFARE Classic sector. Sectors 0-31 (all of a 1K card
+/// and the lower 2K of a 4K card) hold 4 blocks, sectors 32-39 of a 4K card hold 16.
+pub fn sector_blocks(sector: u8) -> Option<(u8, u8)> {
+    match sector {
+        0..=31 => Some((sector * 4, 4)),
+        32..=39 => Some((128 + (sector - 32) * 16, 16)),
+        _ => None,
+    }
+}
+
+/// Absolute block number of block `rel_block` of a MIFARE Classic sector, or `None` if either
+/// is out of range
+pub fn mifare_block(sector: u8, rel_block: u8) -> Option<u8> {
+    let (first, count) = sector_blocks(sector)?;
+    (rel_block < count).then(|| first + rel_block)
+}
+
+/// The access conditions packed into bytes 6-9 of a sector trailer.
+///
+/// Conditions are per block group: blocks 0-2 of a 4 block sector, or blocks 0-4, 5-9 and 10-14
+/// of a 16 block sector; group 3 is the trailer itself. Conditions granting key B assume key B is
+/// not readable, as a readable key B cannot be used to authenticate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessBits {
+    // C1 C2 C3 packed as 0b0000_0123 for each block group
+    conditions: [u8; 4],
+    general_purpose: u8,
+}
+
+impl AccessBits {
+    /// Parse bytes 6-9 of a sector trailer, checking them against their inverted copy
+    pub fn parse(bytes: [u8; 4]) -> Result<Self, InvalidAccessBits> {
+        let [b6, b7, b8, general_purpose] = bytes;
+        let bit = |byte: u8, shift: u8| (byte >> shift) & 1;
+        let mut conditions = [0; 4];
+        for (group, condition) in (0u8..).zip(conditions.iter_mut()) {
+            let c1 = bit(b7, 4 + group);
+            let c2 = bit(b8, group);
+            let c3 = bit(b8, 4 + group);
+            let inverted = [bit(b6, group), bit(b6, 4 + group), bit(b7, group)];
+            if [c1, c2, c3].iter().zip(inverted).any(|(c, n)| c ^ n != 1) {
+                return Err(InvalidAccessBits);
+            }
+            *condition = (c1 << 2) | (c2 << 1) | c3;
+        }
+        Ok(Self {
+            conditions,
+            general_purpose,
+        })
+    }
+
+    /// The C1 C2 C3 bits of a block group, packed as `0b0000_0123`
+    pub fn conditions(&self, group: u8) -> u8 {
+        self.conditions[group.min(3) as usize]
+    }
+
+    /// The user data byte stored alongside the access bits
+    pub fn general_purpose_byte(&self) -> u8 {
+        self.general_purpose
+    }
+
+    /// Key needed to read a data block group; for group 3 (the trailer), to read the access bits
+    pub fn block_read_key(&self, group: u8) -> KeyRequirement {
+        use KeyRequirement::*;
+        match (group, self.conditions(group)) {
+            (3, 0b000..=0b010) => KeyA,
+            (3, _) => KeyAOrB,
+            (_, 0b011 | 0b101) => KeyB,
+            (_, 0b111) => Never,
+            _ => KeyAOrB,
+        }
+    }
+
+    /// Key needed to write a data block group; for group 3 (the trailer), to write the access
+    /// bits
+    pub fn block_write_key(&self, group: u8) -> KeyRequirement {
+        use KeyRequirement::*;
+        match (group, self.conditions(group)) {
+            (3, 0b001) => KeyA,
+            (3, 0b011 | 0b101) => KeyB,
+            (3, _) => Never,
+            (_, 0b000) => KeyAOrB,
+            (_, 0b100 | 0b110 | 0b011) => KeyB,
+            _ => Never,
+        }
+    }
+
+    /// Key needed to overwrite key A in the trailer
+    pub fn key_a_write_key(&self) -> KeyRequirement {
+        match self.conditions(3) {
+            0b000 | 0b001 => KeyRequirement::KeyA,
+            0b100 | 0b011 => KeyRequirement::KeyB,
+            _ => KeyRequirement::Never,
+        }
+    }
+
+    /// Key needed to read key B from the trailer
+    pub fn key_b_read_key(&self) -> KeyRequirement {
+        match self.conditions(3) {
+            0b000..=0b010 => KeyRequirement::KeyA,
+            _ => KeyRequirement::Never,
+        }
+    }
+
+    /// Key needed to overwrite key B in the trailer
+    pub fn key_b_write_key(&self) -> KeyRequirement {
+        match self.conditions(3) {
+            0b000 | 0b001 => KeyRequirement::KeyA,
+            0b100 | 0b011 => KeyRequirement::KeyB,
+            _ => KeyRequirement::Never,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transport_configuration() {
+        let access = AccessBits::parse([0xFF, 0x07, 0x80, 0x69]).unwrap();
+        assert_eq!(access.conditions(0), 0b000);
+        assert_eq!(access.conditions(3), 0b001);
+        assert_eq!(access.block_read_key(0), KeyRequirement::KeyAOrB);
+        assert_eq!(access.block_write_key(2), KeyRequirement::KeyAOrB);
+        assert_eq!(access.block_read_key(3), KeyRequirement::KeyA);
+        assert_eq!(access.block_write_key(3), KeyRequirement::KeyA);
+        assert_eq!(access.key_b_read_key(), KeyRequirement::KeyA);
+        assert_eq!(access.general_purpose_byte(), 0x69);
+    }
+
+    #[test]
+    fn key_b_writes() {
+        let access = AccessBits::parse([0x78, 0x77, 0x88, 0x00]).unwrap();
+        assert_eq!(access.conditions(1), 0b100);
+        assert_eq!(access.conditions(3), 0b011);
+        assert_eq!(access.block_read_key(1), KeyRequirement::KeyAOrB);
+        assert_eq!(access.block_write_key(1), KeyRequirement::KeyB);
+        assert_eq!(access.block_write_key(3), KeyRequirement::KeyB);
+        assert_eq!(access.key_a_write_key(), KeyRequirement::KeyB);
+        assert_eq!(access.key_b_read_key(), KeyRequirement::Never);
+    }
+
+    #[test]
+    fn inverted_copy_mismatch() {
+        assert!(AccessBits::parse([0xFF, 0x07, 0x81, 0x69]).is_err());
+        assert!(AccessBits::parse([0x00, 0x00, 0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn classic_1k_sectors() {
+        assert_eq!(sector_blocks(0), Some((0, 4)));
+        assert_eq!(sector_blocks(1), Some((4, 4)));
+        assert_eq!(sector_blocks(15), Some((60, 4)));
+    }
+
+    #[test]
+    fn classic_4k_sectors() {
+        assert_eq!(sector_blocks(31), Some((124, 4)));
+        assert_eq!(sector_blocks(32), Some((128, 16)));
+        assert_eq!(sector_blocks(33), Some((144, 16)));
+        assert_eq!(sector_blocks(39), Some((240, 16)));
+        assert_eq!(sector_blocks(40), None);
+    }
+
+    #[test]
+    fn absolute_blocks_at_4k_boundary() {
+        assert_eq!(mifare_block(0, 0), Some(0));
+        assert_eq!(mifare_block(15, 3), Some(63));
+        assert_eq!(mifare_block(31, 0), Some(124));
+        assert_eq!(mifare_block(31, 3), Some(127));
+        assert_eq!(mifare_block(31, 4), None);
+        assert_eq!(mifare_block(32, 0), Some(128));
+        assert_eq!(mifare_block(32, 4), Some(132));
+        assert_eq!(mifare_block(32, 15), Some(143));
+        assert_eq!(mifare_block(32, 16), None);
+        assert_eq!(mifare_block(33, 0), Some(144));
+        assert_eq!(mifare_block(39, 15), Some(255));
+        assert_eq!(mifare_block(40, 0), None);
+        // Every block is reached exactly once
+        let blocks: Vec<u8> = (0..40)
+            .flat_map(|sector| (0..16).filter_map(move |rel| mifare_block(sector, rel)))
+            .collect();
+        assert_eq!(blocks, (0..=255).collect::<Vec<u8>>());
+    }
+}
@@ -0,0 +1,114 @@
+//! Reader models recognized from their PC/SC reader names
+
+/// Readers whose vendor pseudo-APDUs this crate knows about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum ReaderModel {
+    /// ACS ACR122U and the ACR122 family
+    Acr122u,
+    Acr1252,
+    /// SCM / Identiv SCL3711
+    Scl3711,
+    /// Other NXP PN532/PN533 based readers
+    Pn53x,
+    /// HID OMNIKEY readers
+    Omnikey,
+    #[default]
+    Unknown,
+}
+
+impl ReaderModel {
+    /// Recognize a reader from the name PC/SC lists it under, e.g.
+    /// `ACS ACR122U PICC Interface 00 00`
+    pub fn from_name(name: &str) -> Self {
+        let name = name.to_ascii_uppercase();
+        if name.contains("ACR122") {
+            ReaderModel::Acr122u
+        } else if name.contains("ACR1252") {
+            ReaderModel::Acr1252
+        } else if name.contains("SCL3711") {
+            ReaderModel::Scl3711
+        } else if name.contains("PN532") || name.contains("PN533") {
+            ReaderModel::Pn53x
+        } else if name.contains("OMNIKEY") {
+            ReaderModel::Omnikey
+        } else {
+            ReaderModel::Unknown
+        }
+    }
+
+    /// Whether the reader answers the ACS `FF 00 48 00 00` firmware version pseudo-APDU.
+    /// Unrecognized readers are given the benefit of the doubt.
+    pub fn supports_firmware_version(&self) -> bool {
+        matches!(self, ReaderModel::Acr122u | ReaderModel::Unknown)
+    }
+
+    /// Whether the reader implements the PC/SC Manage Session pseudo-APDU (`FF C2`) behind
+    /// transparent sessions. The PN53x based readers only relay raw frames through their own
+    /// direct transmit commands. Unrecognized readers are given the benefit of the doubt.
+    pub fn supports_manage_session(&self) -> bool {
+        matches!(
+            self,
+            ReaderModel::Acr1252 | ReaderModel::Omnikey | ReaderModel::Unknown
+        )
+    }
+}
+
+/// Whether a reader name denotes the SAM (secure access module) slot of a reader rather than
+/// its contactless interface, e.g. `ACS ACR1252 Dual Reader [ACR1252 Dual Reader SAM] 00 01`
+pub fn is_sam_slot(name: &str) -> bool {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|word| word.eq_ignore_ascii_case("SAM"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn models_from_reader_names() {
+        let names = [
+            ("ACS ACR122U PICC Interface 00 00", ReaderModel::Acr122u),
+            (
+                "ACS ACR1252 1S CL Reader [ACR1252 1S CL Reader(1)] 00 00",
+                ReaderModel::Acr1252,
+            ),
+            (
+                "SCM Microsystems Inc. SCL3711 reader & NFC device 00 00",
+                ReaderModel::Scl3711,
+            ),
+            ("NXP PN533 NFC Controller 00 00", ReaderModel::Pn53x),
+            (
+                "HID Global OMNIKEY 5022 Smart Card Reader 00 00",
+                ReaderModel::Omnikey,
+            ),
+            ("Yubico YubiKey OTP+FIDO+CCID 00 00", ReaderModel::Unknown),
+        ];
+        for (name, model) in names {
+            assert_eq!(ReaderModel::from_name(name), model, "{name}");
+        }
+        assert!(!ReaderModel::Omnikey.supports_firmware_version());
+        assert!(ReaderModel::Omnikey.supports_manage_session());
+        assert!(!ReaderModel::Acr122u.supports_manage_session());
+    }
+
+    #[test]
+    fn sam_slots_from_reader_names() {
+        let names = [
+            (
+                "ACS ACR1252 Dual Reader [ACR1252 Dual Reader SAM] 00 01",
+                true,
+            ),
+            (
+                "ACS ACR1252 Dual Reader [ACR1252 Dual Reader PICC] 00 00",
+                false,
+            ),
+            ("Identiv uTrust 3700 F CL Reader [SAM] 01 00", true),
+            ("ACS ACR122U PICC Interface 00 00", false),
+            ("Samsung Galaxy NFC Reader 00 00", false),
+        ];
+        for (name, sam) in names {
+            assert_eq!(is_sam_slot(name), sam, "{name}");
+        }
+    }
+}
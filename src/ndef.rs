@@ -0,0 +1,519 @@
+use thiserror::Error;
+
+use crate::command::PcscCodecError;
+
+/// First byte of the capability container on an NDEF formatted Type 2 tag
+pub const NDEF_MAGIC: u8 = 0xE1;
+
+/// Name of the NDEF tag application on a Type 4 tag
+pub const TYPE4_NDEF_AID: [u8; 7] = [0xD2, 0x76, 0x00, 0x00, 0x85, 0x01, 0x01];
+/// File identifier of the capability container file on a Type 4 tag
+pub const TYPE4_CC_FILE: [u8; 2] = [0xE1, 0x03];
+/// Highest offset Read Binary can address in a Type 4 tag's NDEF file
+pub const TYPE4_MAX_OFFSET: u16 = 0x7FFF;
+
+// Tag of the NDEF file control TLV in a Type 4 capability container
+const TLV_NDEF_FILE_CONTROL: u8 = 0x04;
+
+const TLV_NULL: u8 = 0x00;
+const TLV_NDEF_MESSAGE: u8 = 0x03;
+const TLV_TERMINATOR: u8 = 0xFE;
+
+const TNF_WELL_KNOWN: u8 = 0x01;
+
+const URI_PREFIXES: [&str; 36] = [
+    "",
+    "http://www.",
+    "https://www.",
+    "http://",
+    "https://",
+    "tel:",
+    "mailto:",
+    "ftp://anonymous:anonymous@",
+    "ftp://ftp.",
+    "ftps://",
+    "sftp://",
+    "smb://",
+    "nfs://",
+    "ftp://",
+    "dav://",
+    "news:",
+    "telnet://",
+    "imap:",
+    "rtsp://",
+    "urn:",
+    "pop:",
+    "sip:",
+    "sips:",
+    "tftp:",
+    "btspp://",
+    "btl2cap://",
+    "btgoep://",
+    "tcpobex://",
+    "irdaobex://",
+    "file://",
+    "urn:epc:id:",
+    "urn:epc:tag:",
+    "urn:epc:pat:",
+    "urn:epc:raw:",
+    "urn:epc:",
+    "urn:nfc:",
+];
+
+#[derive(Debug, Error)]
+pub enum NdefError {
+    #[error("PC/SC codec error")]
+    Codec(#[from] PcscCodecError),
+    #[error("Tag is not NDEF formatted")]
+    NotFormatted,
+    #[error("Data area ended inside a TLV")]
+    Truncated,
+    #[error("No NDEF message TLV found")]
+    NoMessage,
+    #[error("Malformed NDEF record")]
+    MalformedRecord,
+    #[error("NDEF message does not fit in the tag's data area")]
+    CapacityExceeded,
+    #[error("Tag's capability container denies writing")]
+    ReadOnly,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NdefRecord {
+    Text {
+        language: String,
+        text: String,
+    },
+    Uri(String),
+    Other {
+        tnf: u8,
+        record_type: Vec<u8>,
+        id: Vec<u8>,
+        payload: Vec<u8>,
+    },
+}
+
+/// The capability container in page 3 of an NDEF formatted Type 2 tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapabilityContainer {
+    /// Mapping version, major in the high nibble and minor in the low, e.g. `0x10` for 1.0
+    pub version: u8,
+    /// Size of the data area in bytes
+    pub data_area_len: usize,
+    /// Access conditions, read in the high nibble and write in the low; 0 grants access
+    pub access: u8,
+}
+
+impl CapabilityContainer {
+    pub fn parse(cc: &[u8]) -> Result<Self, NdefError> {
+        match cc {
+            [NDEF_MAGIC, version, size, access, ..] => Ok(Self {
+                version: *version,
+                data_area_len: *size as usize * 8,
+                access: *access,
+            }),
+            _ => Err(NdefError::NotFormatted),
+        }
+    }
+
+    pub fn can_read(&self) -> bool {
+        self.access >> 4 == 0x0
+    }
+
+    pub fn can_write(&self) -> bool {
+        self.access & 0x0F == 0x0
+    }
+}
+
+/// The capability container file of a Type 4 tag, as far as reading NDEF needs it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Type4CapabilityContainer {
+    /// Largest response data the tag sends to a Read Binary
+    pub max_read_len: u16,
+    pub ndef_file_id: [u8; 2],
+    /// Size of the NDEF file, its 2 byte NLEN included
+    pub ndef_file_len: u16,
+    /// Read access condition of the NDEF file; 0 grants access
+    pub read_access: u8,
+    /// Write access condition of the NDEF file; 0 grants access, FF denies it
+    pub write_access: u8,
+}
+
+impl Type4CapabilityContainer {
+    pub fn parse(cc: &[u8]) -> Result<Self, NdefError> {
+        match cc {
+            [_, _, _version, mle_msb, mle_lsb, _, _, TLV_NDEF_FILE_CONTROL, 0x06, id_msb, id_lsb, len_msb, len_lsb, read_access, write_access, ..] => {
+                Ok(Self {
+                    max_read_len: u16::from_be_bytes([*mle_msb, *mle_lsb]),
+                    ndef_file_id: [*id_msb, *id_lsb],
+                    ndef_file_len: u16::from_be_bytes([*len_msb, *len_lsb]),
+                    read_access: *read_access,
+                    write_access: *write_access,
+                })
+            }
+            _ => Err(NdefError::NotFormatted),
+        }
+    }
+}
+
+/// Size in bytes of the data area described by a Type 2 capability container
+pub fn data_area_len(cc: &[u8]) -> Result<usize, NdefError> {
+    CapabilityContainer::parse(cc).map(|cc| cc.data_area_len)
+}
+
+/// Find the first NDEF message TLV in a Type 2 data area and return its value
+pub fn find_message(area: &[u8]) -> Result<&[u8], NdefError> {
+    let mut offset = 0;
+    loop {
+        match area.get(offset) {
+            None => return Err(NdefError::Truncated),
+            Some(&TLV_NULL) => offset += 1,
+            Some(&TLV_TERMINATOR) => return Err(NdefError::NoMessage),
+            Some(&tag) => {
+                let (len, header) = match area.get(offset + 1) {
+                    None => return Err(NdefError::Truncated),
+                    Some(0xFF) => match area.get(offset + 2..offset + 4) {
+                        Some(&[msb, lsb]) => (u16::from_be_bytes([msb, lsb]) as usize, 4),
+                        _ => return Err(NdefError::Truncated),
+                    },
+                    Some(&len) => (len as usize, 2),
+                };
+                let value_start = offset + header;
+                let value = area
+                    .get(value_start..value_start + len)
+                    .ok_or(NdefError::Truncated)?;
+                if tag == TLV_NDEF_MESSAGE {
+                    return Ok(value);
+                }
+                offset = value_start + len;
+            }
+        }
+    }
+}
+
+/// Decode the records of an NDEF message
+pub fn parse_message(message: &[u8]) -> Result<Vec<NdefRecord>, NdefError> {
+    let mut records = vec![];
+    let mut rest = message;
+    while !rest.is_empty() {
+        let (record, len) = parse_record(rest)?;
+        records.push(record);
+        rest = &rest[len..];
+    }
+    Ok(records)
+}
+
+// Decode one record, returning it with the number of bytes consumed
+fn parse_record(bytes: &[u8]) -> Result<(NdefRecord, usize), NdefError> {
+    let header = *bytes.first().ok_or(NdefError::MalformedRecord)?;
+    let short_record = header & 0x10 != 0;
+    let has_id = header & 0x08 != 0;
+    let tnf = header & 0x07;
+    let type_len = *bytes.get(1).ok_or(NdefError::MalformedRecord)? as usize;
+    let (payload_len, mut offset) = if short_record {
+        (*bytes.get(2).ok_or(NdefError::MalformedRecord)? as usize, 3)
+    } else {
+        let len = bytes.get(2..6).ok_or(NdefError::MalformedRecord)?;
+        (u32::from_be_bytes(len.try_into().unwrap()) as usize, 6)
+    };
+    let id_len = if has_id {
+        offset += 1;
+        *bytes.get(offset - 1).ok_or(NdefError::MalformedRecord)? as usize
+    } else {
+        0
+    };
+    let mut field = |len: usize| {
+        let value = bytes
+            .get(offset..offset + len)
+            .ok_or(NdefError::MalformedRecord);
+        offset += len;
+        value
+    };
+    let record_type = field(type_len)?;
+    let id = field(id_len)?;
+    let payload = field(payload_len)?;
+    let record = match (tnf, record_type) {
+        (TNF_WELL_KNOWN, b"T") => parse_text(payload)?,
+        (TNF_WELL_KNOWN, b"U") => parse_uri(payload)?,
+        _ => NdefRecord::Other {
+            tnf,
+            record_type: record_type.to_vec(),
+            id: id.to_vec(),
+            payload: payload.to_vec(),
+        },
+    };
+    Ok((record, offset))
+}
+
+fn parse_text(payload: &[u8]) -> Result<NdefRecord, NdefError> {
+    let status = *payload.first().ok_or(NdefError::MalformedRecord)?;
+    let language_len = (status & 0x3F) as usize;
+    let language = payload
+        .get(1..1 + language_len)
+        .ok_or(NdefError::MalformedRecord)?;
+    let language = String::from_utf8(language.to_vec()).map_err(|_| NdefError::MalformedRecord)?;
+    let text = &payload[1 + language_len..];
+    let text = if status & 0x80 != 0 {
+        if !text.len().is_multiple_of(2) {
+            return Err(NdefError::MalformedRecord);
+        }
+        let units: Vec<u16> = text
+            .chunks_exact(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+            .collect();
+        String::from_utf16(&units).map_err(|_| NdefError::MalformedRecord)?
+    } else {
+        String::from_utf8(text.to_vec()).map_err(|_| NdefError::MalformedRecord)?
+    };
+    Ok(NdefRecord::Text { language, text })
+}
+
+fn parse_uri(payload: &[u8]) -> Result<NdefRecord, NdefError> {
+    let (&code, rest) = payload.split_first().ok_or(NdefError::MalformedRecord)?;
+    let prefix = URI_PREFIXES.get(code as usize).copied().unwrap_or("");
+    let rest = std::str::from_utf8(rest).map_err(|_| NdefError::MalformedRecord)?;
+    Ok(NdefRecord::Uri(format!("{prefix}{rest}")))
+}
+
+/// Encode records into an NDEF message
+pub fn encode_message(records: &[NdefRecord]) -> Result<Vec<u8>, NdefError> {
+    let mut message = vec![];
+    for (i, record) in records.iter().enumerate() {
+        let mut header = 0;
+        if i == 0 {
+            header |= 0x80; // MB
+        }
+        if i == records.len() - 1 {
+            header |= 0x40; // ME
+        }
+        encode_record(record, header, &mut message)?;
+    }
+    Ok(message)
+}
+
+/// Wrap an NDEF message in its TLV, followed by a terminator TLV when there is room, padded to
+/// whole pages. Fails if the result does not fit a data area of `capacity` bytes.
+pub fn encode_area(records: &[NdefRecord], capacity: usize) -> Result<Vec<u8>, NdefError> {
+    let message = encode_message(records)?;
+    let mut area = vec![TLV_NDEF_MESSAGE];
+    if message.len() < 0xFF {
+        area.push(message.len() as u8);
+    } else {
+        let len = u16::try_from(message.len()).map_err(|_| NdefError::CapacityExceeded)?;
+        area.push(0xFF);
+        area.extend(len.to_be_bytes());
+    }
+    area.extend(message);
+    if area.len() > capacity {
+        return Err(NdefError::CapacityExceeded);
+    }
+    // The terminator may be left out when the message ends exactly at the end of the data area
+    if area.len() < capacity {
+        area.push(TLV_TERMINATOR);
+    }
+    while !area.len().is_multiple_of(4) && area.len() < capacity {
+        area.push(TLV_NULL);
+    }
+    Ok(area)
+}
+
+fn encode_record(record: &NdefRecord, header: u8, output: &mut Vec<u8>) -> Result<(), NdefError> {
+    let (tnf, record_type, id, payload): (u8, &[u8], &[u8], Vec<u8>) = match record {
+        NdefRecord::Text { language, text } => {
+            if language.len() > 0x3F {
+                return Err(NdefError::MalformedRecord);
+            }
+            let mut payload = vec![language.len() as u8];
+            payload.extend(language.as_bytes());
+            payload.extend(text.as_bytes());
+            (TNF_WELL_KNOWN, b"T", &[], payload)
+        }
+        NdefRecord::Uri(uri) => {
+            let (code, prefix) = URI_PREFIXES
+                .iter()
+                .enumerate()
+                .skip(1)
+                .filter(|(_, prefix)| uri.starts_with(*prefix))
+                .max_by_key(|(_, prefix)| prefix.len())
+                .unwrap_or((0, &""));
+            let mut payload = vec![code as u8];
+            payload.extend(&uri.as_bytes()[prefix.len()..]);
+            (TNF_WELL_KNOWN, b"U", &[], payload)
+        }
+        NdefRecord::Other {
+            tnf,
+            record_type,
+            id,
+            payload,
+        } => (*tnf, &record_type[..], &id[..], payload.clone()),
+    };
+    let type_len = u8::try_from(record_type.len()).map_err(|_| NdefError::MalformedRecord)?;
+    let id_len = u8::try_from(id.len()).map_err(|_| NdefError::MalformedRecord)?;
+    let mut header = header | (tnf & 0x07);
+    if !id.is_empty() {
+        header |= 0x08; // IL
+    }
+    if payload.len() <= u8::MAX as usize {
+        output.extend([header | 0x10, type_len, payload.len() as u8]); // SR
+    } else {
+        let payload_len = u32::try_from(payload.len()).map_err(|_| NdefError::MalformedRecord)?;
+        output.extend([header, type_len]);
+        output.extend(payload_len.to_be_bytes());
+    }
+    if !id.is_empty() {
+        output.push(id_len);
+    }
+    output.extend(record_type);
+    output.extend(id);
+    output.extend(payload);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // NTAG213 pages 4-11 holding a lock control TLV followed by a URI record
+    const URI_DUMP: [u8; 32] = [
+        0x01, 0x03, 0xA0, 0x0C, 0x34, 0x03, 0x10, 0xD1, 0x01, 0x0C, 0x55, 0x02, 0x65, 0x78, 0x61,
+        0x6D, 0x70, 0x6C, 0x65, 0x2E, 0x63, 0x6F, 0x6D, 0xFE, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ];
+
+    // Ultralight pages 4-7 holding a text record
+    const TEXT_DUMP: [u8; 16] = [
+        0x03, 0x0C, 0xD1, 0x01, 0x08, 0x54, 0x02, 0x65, 0x6E, 0x68, 0x65, 0x6C, 0x6C, 0x6F, 0xFE,
+        0x00,
+    ];
+
+    #[test]
+    fn uri_record_from_dump() {
+        let message = find_message(&URI_DUMP).unwrap();
+        let records = parse_message(message).unwrap();
+        assert_eq!(
+            records,
+            vec![NdefRecord::Uri("https://www.example.com".to_string())]
+        );
+    }
+
+    #[test]
+    fn text_record_from_dump() {
+        let message = find_message(&TEXT_DUMP).unwrap();
+        let records = parse_message(message).unwrap();
+        assert_eq!(
+            records,
+            vec![NdefRecord::Text {
+                language: "en".to_string(),
+                text: "hello".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn message_cut_short() {
+        assert!(matches!(
+            find_message(&URI_DUMP[..12]),
+            Err(NdefError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn empty_tag() {
+        assert!(matches!(
+            find_message(&[0x03, 0x00, 0xFE]),
+            Ok(message) if message.is_empty()
+        ));
+        assert!(matches!(
+            find_message(&[0x00, 0xFE]),
+            Err(NdefError::NoMessage)
+        ));
+    }
+
+    #[test]
+    fn encode_matches_dump() {
+        let records = vec![NdefRecord::Text {
+            language: "en".to_string(),
+            text: "hello".to_string(),
+        }];
+        assert_eq!(encode_area(&records, 48).unwrap(), TEXT_DUMP.to_vec());
+    }
+
+    #[test]
+    fn round_trip() {
+        let records = vec![
+            NdefRecord::Uri("https://www.example.com".to_string()),
+            NdefRecord::Text {
+                language: "en".to_string(),
+                text: "hello".to_string(),
+            },
+            NdefRecord::Other {
+                tnf: 0x02,
+                record_type: b"text/plain".to_vec(),
+                id: b"1".to_vec(),
+                payload: vec![0x61; 300],
+            },
+        ];
+        let area = encode_area(&records, 496).unwrap();
+        assert!(area.len().is_multiple_of(4));
+        let decoded = parse_message(find_message(&area).unwrap()).unwrap();
+        assert_eq!(decoded, records);
+    }
+
+    #[test]
+    fn terminator_only_when_room() {
+        let records = vec![NdefRecord::Uri("https://www.example.com".to_string())];
+        // TLV header (2) + record (16) fills an 18 byte area exactly
+        let area = encode_area(&records, 18).unwrap();
+        assert_eq!(area.len(), 18);
+        assert_ne!(area.last(), Some(&0xFE));
+        let area = encode_area(&records, 24).unwrap();
+        assert_eq!(area[18], 0xFE);
+        assert!(matches!(
+            encode_area(&records, 17),
+            Err(NdefError::CapacityExceeded)
+        ));
+    }
+
+    #[test]
+    fn capability_container() {
+        assert_eq!(data_area_len(&[0xE1, 0x10, 0x12, 0x00]).unwrap(), 144);
+        assert!(matches!(
+            data_area_len(&[0x00, 0x00, 0x00, 0x00]),
+            Err(NdefError::NotFormatted)
+        ));
+    }
+
+    #[test]
+    fn ntag_capability_containers() {
+        for (cc, len) in [
+            ([0xE1, 0x10, 0x12, 0x00], 144), // NTAG213
+            ([0xE1, 0x10, 0x3E, 0x00], 496), // NTAG215
+            ([0xE1, 0x10, 0x6D, 0x00], 872), // NTAG216
+        ] {
+            let cc = CapabilityContainer::parse(&cc).unwrap();
+            assert_eq!(cc.version, 0x10);
+            assert_eq!(cc.data_area_len, len);
+            assert!(cc.can_read() && cc.can_write());
+        }
+        let locked = CapabilityContainer::parse(&[0xE1, 0x10, 0x12, 0x0F]).unwrap();
+        assert!(locked.can_read());
+        assert!(!locked.can_write());
+    }
+
+    #[test]
+    fn type4_capability_container() {
+        let cc = Type4CapabilityContainer::parse(&[
+            0x00, 0x0F, 0x20, 0x00, 0x3B, 0x00, 0x34, 0x04, 0x06, 0xE1, 0x04, 0x00, 0x80, 0x00,
+            0xFF,
+        ])
+        .unwrap();
+        assert_eq!(cc.max_read_len, 0x3B);
+        assert_eq!(cc.ndef_file_id, [0xE1, 0x04]);
+        assert_eq!(cc.ndef_file_len, 0x80);
+        assert_eq!(cc.write_access, 0xFF);
+        assert!(matches!(
+            Type4CapabilityContainer::parse(&[0x00, 0x0F, 0x20]),
+            Err(NdefError::NotFormatted)
+        ));
+    }
+}
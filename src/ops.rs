@@ -0,0 +1,170 @@
+//! Block access routed to the command set of each card family
+
+use crate::atr::{CardFamily, CardName, TagType};
+use crate::command::{KeyType, PcscCodecError, PcscCommand};
+use crate::transport::ApduTransport;
+use crate::RfidTag;
+
+/// Block operations with a uniform interface across card families, obtained with
+/// [`RfidTag::ops`]. Operations a family has no equivalent for fail with
+/// [`PcscCodecError::UnsupportedCard`].
+pub trait CardOps<T: ApduTransport> {
+    /// Read one block: 16 bytes on MIFARE Classic, a 4 byte page on Ultralight/NTAG, the
+    /// reader's native block size on other storage cards
+    fn read_block(&self, tag: &RfidTag<T>, block: u8) -> Result<Vec<u8>, PcscCodecError> {
+        let _ = (tag, block);
+        Err(PcscCodecError::UnsupportedCard)
+    }
+
+    /// Write one block, `data` sized as [`CardOps::read_block`] returns it
+    fn write_block(&self, tag: &RfidTag<T>, block: u8, data: &[u8]) -> Result<(), PcscCodecError> {
+        let _ = (tag, block, data);
+        Err(PcscCodecError::UnsupportedCard)
+    }
+
+    /// Authenticate access to `block` with the key in reader slot `key_slot`
+    fn authenticate(
+        &self,
+        tag: &RfidTag<T>,
+        block: u8,
+        key_type: KeyType,
+        key_slot: u8,
+    ) -> Result<(), PcscCodecError> {
+        let _ = (tag, block, key_type, key_slot);
+        Err(PcscCodecError::UnsupportedCard)
+    }
+}
+
+// MIFARE Classic and Plus in security level 1: 16 byte blocks behind key A/B authentication
+struct ClassicOps;
+
+impl<T: ApduTransport> CardOps<T> for ClassicOps {
+    fn read_block(&self, tag: &RfidTag<T>, block: u8) -> Result<Vec<u8>, PcscCodecError> {
+        tag.read_block(block).map(Vec::from)
+    }
+
+    fn write_block(&self, tag: &RfidTag<T>, block: u8, data: &[u8]) -> Result<(), PcscCodecError> {
+        let data: [u8; 16] = data.try_into().map_err(|_| wrong_length(data, 16))?;
+        tag.run_command_checked(PcscCommand::update_binary(block as u16, data.to_vec()))?;
+        Ok(())
+    }
+
+    fn authenticate(
+        &self,
+        tag: &RfidTag<T>,
+        block: u8,
+        key_type: KeyType,
+        key_slot: u8,
+    ) -> Result<(), PcscCodecError> {
+        tag.run_command_checked(PcscCommand::general_authenticate(
+            block as u16,
+            key_type,
+            key_slot,
+        ))?;
+        Ok(())
+    }
+}
+
+// MIFARE Ultralight and NTAG: 4 byte pages, no key slot authentication
+struct UltralightOps;
+
+impl<T: ApduTransport> CardOps<T> for UltralightOps {
+    fn read_block(&self, tag: &RfidTag<T>, block: u8) -> Result<Vec<u8>, PcscCodecError> {
+        // A read returns four pages from `block` on
+        Ok(tag.read_page(block)?[..4].to_vec())
+    }
+
+    fn write_block(&self, tag: &RfidTag<T>, block: u8, data: &[u8]) -> Result<(), PcscCodecError> {
+        let data = data.try_into().map_err(|_| wrong_length(data, 4))?;
+        tag.write_page(block, data)
+    }
+}
+
+// Other storage cards, e.g. ISO15693, through plain Read/Update Binary
+struct StorageOps;
+
+impl<T: ApduTransport> CardOps<T> for StorageOps {
+    fn read_block(&self, tag: &RfidTag<T>, block: u8) -> Result<Vec<u8>, PcscCodecError> {
+        tag.run_command_checked(PcscCommand::read_binary(block as u16, 0))
+            .map(|response| response.into_parts().0)
+    }
+
+    fn write_block(&self, tag: &RfidTag<T>, block: u8, data: &[u8]) -> Result<(), PcscCodecError> {
+        tag.run_command_checked(PcscCommand::update_binary(block as u16, data.to_vec()))?;
+        Ok(())
+    }
+}
+
+// ISO14443-4 cards such as DESFire, driven by application commands rather than blocks
+struct ApplicationOps;
+
+impl<T: ApduTransport> CardOps<T> for ApplicationOps {}
+
+fn wrong_length(data: &[u8], expected: usize) -> PcscCodecError {
+    if data.len() < expected {
+        PcscCodecError::TooShort
+    } else {
+        PcscCodecError::TooLong
+    }
+}
+
+/// The operations for a card with the given ATR card name and tag type
+pub(crate) fn for_card<'a, T: ApduTransport + 'a>(
+    card_name: Option<CardName>,
+    tag_type: Option<TagType>,
+) -> &'a dyn CardOps<T> {
+    match (card_name.map(|name| name.family()), tag_type) {
+        (Some(CardFamily::MifareClassic), _) => &ClassicOps,
+        (Some(CardFamily::MifareUltralight), _) => &UltralightOps,
+        (_, Some(TagType::StorageCard)) => &StorageOps,
+        _ => &ApplicationOps,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::mock::MockTransport;
+    use crate::RfidTag;
+
+    use super::*;
+
+    const MIFARE_1K_ATR: [u8; 20] = [
+        0x3B, 0x8F, 0x80, 0x01, 0x80, 0x4F, 0x0C, 0xA0, 0x00, 0x00, 0x03, 0x06, 0x03, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x6A,
+    ];
+
+    #[test]
+    fn mifare_1k_routes_to_classic() {
+        let mut reply = vec![0xAB; 16];
+        reply.extend([0x90, 0x00]);
+        let tag = RfidTag::with_transport(
+            MockTransport::new([vec![0x90, 0x00], reply]),
+            &MIFARE_1K_ATR,
+        );
+        let ops = tag.ops();
+        ops.authenticate(&tag, 4, KeyType::MifareA, 0).unwrap();
+        assert_eq!(ops.read_block(&tag, 4).unwrap(), vec![0xAB; 16]);
+        assert!(matches!(
+            ops.write_block(&tag, 4, &[0x00; 4]),
+            Err(PcscCodecError::TooShort)
+        ));
+        assert_eq!(
+            tag.card.sent(),
+            vec![
+                vec![0xFF, 0x86, 0x00, 0x00, 0x05, 0x01, 0x00, 0x04, 0x60, 0x00],
+                vec![0xFF, 0xB0, 0x00, 0x04, 0x10],
+            ]
+        );
+    }
+
+    #[test]
+    fn iso14443_4_has_no_blocks() {
+        // Generic ISO14443-4 ATR with no historical bytes
+        let tag = RfidTag::with_transport(MockTransport::new([]), &[0x3B, 0x80, 0x80, 0x01, 0x01]);
+        assert!(matches!(
+            tag.ops().read_block(&tag, 0),
+            Err(PcscCodecError::UnsupportedCard)
+        ));
+        assert!(tag.card.sent().is_empty());
+    }
+}
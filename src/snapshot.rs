@@ -0,0 +1,61 @@
+//! Point-in-time records of detected tags, e.g. for audit logs
+
+use std::time::SystemTime;
+
+use crate::atr::{CardName, Standard, TagType};
+use crate::command::PcscCodecError;
+use crate::transport::ApduTransport;
+use crate::RfidTag;
+
+/// What was known about a tag when it was captured
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagSnapshot {
+    pub captured_at: SystemTime,
+    pub tag_type: Option<TagType>,
+    pub standard: Option<Standard>,
+    pub card_name: Option<CardName>,
+    pub uid: Vec<u8>,
+    pub atr: Vec<u8>,
+}
+
+impl<T: ApduTransport> TryFrom<&RfidTag<T>> for TagSnapshot {
+    type Error = PcscCodecError;
+
+    /// Read the UID and bundle it with what the ATR told
+    fn try_from(tag: &RfidTag<T>) -> Result<Self, Self::Error> {
+        let uid = tag.uid_info()?.uid;
+        let info = tag.atr_info();
+        Ok(Self {
+            captured_at: SystemTime::now(),
+            tag_type: info.tag_type,
+            standard: info.standard,
+            card_name: info.card_name,
+            uid,
+            atr: info.atr.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atr::AtrBuilder;
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn snapshot_of_mock_tag() {
+        let atr = AtrBuilder::new(Standard::Iso14443APart3, CardName::MifareStandard1K).build();
+        let tag = RfidTag::with_transport(
+            MockTransport::new([vec![0x04, 0xA2, 0x3B, 0x11, 0x90, 0x00]]),
+            &atr,
+        );
+        let before = SystemTime::now();
+        let snapshot = TagSnapshot::try_from(&tag).unwrap();
+        assert!(snapshot.captured_at >= before);
+        assert_eq!(snapshot.tag_type, Some(TagType::StorageCard));
+        assert_eq!(snapshot.standard, Some(Standard::Iso14443APart3));
+        assert_eq!(snapshot.card_name, Some(CardName::MifareStandard1K));
+        assert_eq!(snapshot.uid, [0x04, 0xA2, 0x3B, 0x11]);
+        assert_eq!(snapshot.atr, atr);
+    }
+}
@@ -0,0 +1,125 @@
+/// Iterator over the BER-TLV data objects in a buffer, yielding `(tag, value)` pairs.
+///
+/// Multi-byte tags are returned with all their bytes packed big-endian, e.g. `0xBF0C`. Padding
+/// bytes (`00` or `FF`) between objects are skipped, and iteration stops at the first malformed
+/// object.
+#[derive(Debug, Clone)]
+pub struct TlvIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> TlvIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn parse_tag(&mut self) -> Option<u32> {
+        let (&first, _) = self.data.split_first()?;
+        let mut tag = first as u32;
+        let mut len = 1;
+        // A low tag number of 0x1F means the number continues in the following bytes
+        if first & 0x1F == 0x1F {
+            loop {
+                let &byte = self.data.get(len)?;
+                // Tags wider than a u32 are not supported
+                if len == 4 {
+                    return None;
+                }
+                tag = (tag << 8) | byte as u32;
+                len += 1;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+        }
+        self.data = &self.data[len..];
+        Some(tag)
+    }
+
+    fn parse_len(&mut self) -> Option<usize> {
+        let (&first, rest) = self.data.split_first()?;
+        if first < 0x80 {
+            self.data = rest;
+            return Some(first as usize);
+        }
+        let count = (first & 0x7F) as usize;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let bytes = rest.get(..count)?;
+        self.data = &rest[count..];
+        Some(bytes.iter().fold(0, |len, &b| (len << 8) | b as usize))
+    }
+}
+
+impl<'a> Iterator for TlvIter<'a> {
+    type Item = (u32, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((0x00 | 0xFF, rest)) = self.data.split_first() {
+            self.data = rest;
+        }
+        let item = self
+            .parse_tag()
+            .zip(self.parse_len())
+            .and_then(|(tag, len)| {
+                let value = self.data.get(..len)?;
+                self.data = &self.data[len..];
+                Some((tag, value))
+            });
+        if item.is_none() {
+            self.data = &[];
+        }
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // FCI returned when selecting 2PAY.SYS.DDF01 on a Visa contactless card
+    const PPSE_FCI: [u8; 37] = [
+        0x6F, 0x23, 0x84, 0x0E, 0x32, 0x50, 0x41, 0x59, 0x2E, 0x53, 0x59, 0x53, 0x2E, 0x44, 0x44,
+        0x46, 0x30, 0x31, 0xA5, 0x11, 0xBF, 0x0C, 0x0E, 0x61, 0x0C, 0x4F, 0x07, 0xA0, 0x00, 0x00,
+        0x00, 0x03, 0x10, 0x10, 0x87, 0x01, 0x01,
+    ];
+
+    #[test]
+    fn nested_fci() {
+        let (tag, fci) = TlvIter::new(&PPSE_FCI).next().unwrap();
+        assert_eq!(tag, 0x6F);
+        let objects: Vec<_> = TlvIter::new(fci).collect();
+        assert_eq!(objects[0], (0x84, &b"2PAY.SYS.DDF01"[..]));
+        assert_eq!(objects[1].0, 0xA5);
+        let (tag, template) = TlvIter::new(objects[1].1).next().unwrap();
+        assert_eq!(tag, 0xBF0C);
+        let (tag, entry) = TlvIter::new(template).next().unwrap();
+        assert_eq!(tag, 0x61);
+        let aid: Vec<_> = TlvIter::new(entry).collect();
+        assert_eq!(
+            aid,
+            vec![
+                (0x4F, &[0xA0, 0x00, 0x00, 0x00, 0x03, 0x10, 0x10][..]),
+                (0x87, &[0x01][..])
+            ]
+        );
+    }
+
+    #[test]
+    fn long_form_length() {
+        let mut data = vec![0x53, 0x81, 0x80];
+        data.extend([0xAB; 0x80]);
+        data.extend([0x00, 0x00, 0x9F, 0x36, 0x82, 0x00, 0x02, 0x00, 0x1C]);
+        let objects: Vec<_> = TlvIter::new(&data).collect();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].1.len(), 0x80);
+        assert_eq!(objects[1], (0x9F36, &[0x00, 0x1C][..]));
+    }
+
+    #[test]
+    fn stops_on_truncated_value() {
+        let objects: Vec<_> = TlvIter::new(&[0x84, 0x01, 0x01, 0x85, 0x05, 0x01]).collect();
+        assert_eq!(objects, vec![(0x84, &[0x01][..])]);
+    }
+}
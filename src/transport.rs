@@ -0,0 +1,295 @@
+use std::ffi::CStr;
+
+use pcsc::{
+    Attribute, Card, Context, Disposition, Error as PcscError, Protocol, Protocols, ShareMode,
+    Status,
+};
+
+/// The link an [`RfidTag`](crate::RfidTag) exchanges APDUs over, a connected [`Card`] by default
+pub trait ApduTransport {
+    /// Send `apdu` and fill `response` with the reply. `response` arrives empty, with its
+    /// capacity set to the expected response length.
+    fn transmit(&self, apdu: &[u8], response: &mut Vec<u8>) -> Result<(), PcscError>;
+
+    /// Whether the card is still present, asked of the reader without any exchange with the
+    /// card. Transports that cannot tell report it present.
+    fn is_present(&self) -> Result<bool, PcscError> {
+        Ok(true)
+    }
+
+    /// Reconnect to the card, treating it as `initialization` says, and return its new ATR.
+    /// Transports without connections fail with [`PcscError::UnsupportedFeature`].
+    fn reconnect(&mut self, initialization: Disposition) -> Result<Vec<u8>, PcscError> {
+        let _ = initialization;
+        Err(PcscError::UnsupportedFeature)
+    }
+
+    /// Read a reader or card attribute. Transports without attributes fail with
+    /// [`PcscError::UnsupportedFeature`].
+    fn get_attribute(&self, attribute: Attribute) -> Result<Vec<u8>, PcscError> {
+        let _ = attribute;
+        Err(PcscError::UnsupportedFeature)
+    }
+
+    /// Set a reader or card attribute. Transports without attributes fail with
+    /// [`PcscError::UnsupportedFeature`].
+    fn set_attribute(&self, attribute: Attribute, value: &[u8]) -> Result<(), PcscError> {
+        let _ = (attribute, value);
+        Err(PcscError::UnsupportedFeature)
+    }
+
+    /// Send a reader escape command and return the reader's reply. Transports without a
+    /// reader fail with [`PcscError::UnsupportedFeature`].
+    fn control(&self, control_code: u32, send: &[u8]) -> Result<Vec<u8>, PcscError> {
+        let _ = (control_code, send);
+        Err(PcscError::UnsupportedFeature)
+    }
+}
+
+impl ApduTransport for Card {
+    fn transmit(&self, apdu: &[u8], response: &mut Vec<u8>) -> Result<(), PcscError> {
+        response.resize(response.capacity(), 0);
+        let len = Card::transmit(self, apdu, response)?.len();
+        response.truncate(len);
+        Ok(())
+    }
+
+    fn is_present(&self) -> Result<bool, PcscError> {
+        Ok(self.status2_owned()?.status().contains(Status::PRESENT))
+    }
+
+    fn reconnect(&mut self, initialization: Disposition) -> Result<Vec<u8>, PcscError> {
+        // Keep the protocol already negotiated
+        let protocols = match self.status2_owned()?.protocol2() {
+            Some(Protocol::T0) => Protocols::T0,
+            Some(Protocol::T1) => Protocols::T1,
+            Some(Protocol::RAW) => Protocols::RAW,
+            None => Protocols::ANY,
+        };
+        Card::reconnect(self, ShareMode::Shared, protocols, initialization)?;
+        Ok(self.status2_owned()?.atr().to_vec())
+    }
+
+    fn get_attribute(&self, attribute: Attribute) -> Result<Vec<u8>, PcscError> {
+        self.get_attribute_owned(attribute)
+    }
+
+    fn set_attribute(&self, attribute: Attribute, value: &[u8]) -> Result<(), PcscError> {
+        Card::set_attribute(self, attribute, value)
+    }
+
+    fn control(&self, control_code: u32, send: &[u8]) -> Result<Vec<u8>, PcscError> {
+        let mut buf = vec![0; pcsc::MAX_BUFFER_SIZE];
+        let len = Card::control(self, control_code.into(), send, &mut buf)?.len();
+        buf.truncate(len);
+        Ok(buf)
+    }
+}
+
+// Where a reader connects to its card, so connections can be checked without a reader
+pub(crate) trait Connector {
+    type Card;
+
+    fn connect(
+        &self,
+        reader: &CStr,
+        share_mode: ShareMode,
+        protocols: Protocols,
+    ) -> Result<Self::Card, PcscError>;
+}
+
+impl Connector for Context {
+    type Card = Card;
+
+    fn connect(
+        &self,
+        reader: &CStr,
+        share_mode: ShareMode,
+        protocols: Protocols,
+    ) -> Result<Card, PcscError> {
+        Context::connect(self, reader, share_mode, protocols)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::collections::{HashMap, VecDeque};
+    use std::ffi::CString;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// Hands out the given transports in order, recording how each connection was asked for.
+    /// Transports are shared so a test can inspect them after the connection is dropped.
+    #[derive(Default)]
+    pub(crate) struct MockConnector {
+        cards: Mutex<VecDeque<Arc<MockTransport>>>,
+        connections: Mutex<Vec<(CString, ShareMode, Protocols)>>,
+    }
+
+    impl MockConnector {
+        pub(crate) fn new(cards: impl IntoIterator<Item = Arc<MockTransport>>) -> Self {
+            Self {
+                cards: Mutex::new(cards.into_iter().collect()),
+                connections: Mutex::default(),
+            }
+        }
+
+        pub(crate) fn connections(&self) -> Vec<(CString, ShareMode, Protocols)> {
+            self.connections.lock().unwrap().clone()
+        }
+    }
+
+    impl Connector for MockConnector {
+        type Card = Arc<MockTransport>;
+
+        fn connect(
+            &self,
+            reader: &CStr,
+            share_mode: ShareMode,
+            protocols: Protocols,
+        ) -> Result<Arc<MockTransport>, PcscError> {
+            self.connections
+                .lock()
+                .unwrap()
+                .push((reader.to_owned(), share_mode, protocols));
+            self.cards
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or(PcscError::NoSmartcard)
+        }
+    }
+
+    /// Replays canned responses in order, recording every APDU sent and the receive buffer
+    /// capacity it was given. A response larger than that capacity fails, as it would on a
+    /// real reader.
+    #[derive(Default)]
+    pub(crate) struct MockTransport {
+        replies: Mutex<VecDeque<Result<Vec<u8>, PcscError>>>,
+        sent: Mutex<Vec<Vec<u8>>>,
+        capacities: Mutex<Vec<usize>>,
+        status_error: Mutex<Option<PcscError>>,
+        dispositions: Mutex<Vec<Disposition>>,
+        attributes: Mutex<HashMap<Attribute, Vec<u8>>>,
+        controls: Mutex<Vec<(u32, Vec<u8>)>>,
+    }
+
+    impl MockTransport {
+        pub(crate) fn new(replies: impl IntoIterator<Item = Vec<u8>>) -> Self {
+            Self::with_results(replies.into_iter().map(Ok))
+        }
+
+        pub(crate) fn with_results(
+            replies: impl IntoIterator<Item = Result<Vec<u8>, PcscError>>,
+        ) -> Self {
+            Self {
+                replies: Mutex::new(replies.into_iter().collect()),
+                sent: Mutex::default(),
+                capacities: Mutex::default(),
+                status_error: Mutex::default(),
+                dispositions: Mutex::default(),
+                attributes: Mutex::default(),
+                controls: Mutex::default(),
+            }
+        }
+
+        pub(crate) fn dispositions(&self) -> Vec<Disposition> {
+            self.dispositions.lock().unwrap().clone()
+        }
+
+        /// Fail presence checks with `error` from now on
+        pub(crate) fn set_status_error(&self, error: PcscError) {
+            *self.status_error.lock().unwrap() = Some(error);
+        }
+
+        pub(crate) fn sent(&self) -> Vec<Vec<u8>> {
+            self.sent.lock().unwrap().clone()
+        }
+
+        pub(crate) fn capacities(&self) -> Vec<usize> {
+            self.capacities.lock().unwrap().clone()
+        }
+
+        pub(crate) fn controls(&self) -> Vec<(u32, Vec<u8>)> {
+            self.controls.lock().unwrap().clone()
+        }
+    }
+
+    impl ApduTransport for MockTransport {
+        fn transmit(&self, apdu: &[u8], response: &mut Vec<u8>) -> Result<(), PcscError> {
+            self.sent.lock().unwrap().push(apdu.to_vec());
+            self.capacities.lock().unwrap().push(response.capacity());
+            let reply = self
+                .replies
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("no canned response left")?;
+            // Like SCardTransmit, refuse a reply that does not fit the receive buffer
+            if reply.len() > response.capacity() {
+                return Err(PcscError::InsufficientBuffer);
+            }
+            response.extend(reply);
+            Ok(())
+        }
+
+        fn is_present(&self) -> Result<bool, PcscError> {
+            match *self.status_error.lock().unwrap() {
+                Some(error) => Err(error),
+                None => Ok(true),
+            }
+        }
+
+        /// Answers with the next canned response as the new ATR
+        fn reconnect(&mut self, initialization: Disposition) -> Result<Vec<u8>, PcscError> {
+            self.dispositions.lock().unwrap().push(initialization);
+            self.replies
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("no canned response left")
+        }
+
+        /// Holds whatever attributes were set; others are unsupported
+        fn get_attribute(&self, attribute: Attribute) -> Result<Vec<u8>, PcscError> {
+            self.attributes
+                .lock()
+                .unwrap()
+                .get(&attribute)
+                .cloned()
+                .ok_or(PcscError::UnsupportedFeature)
+        }
+
+        fn set_attribute(&self, attribute: Attribute, value: &[u8]) -> Result<(), PcscError> {
+            self.attributes
+                .lock()
+                .unwrap()
+                .insert(attribute, value.to_vec());
+            Ok(())
+        }
+
+        /// Answers with the next canned response
+        fn control(&self, control_code: u32, send: &[u8]) -> Result<Vec<u8>, PcscError> {
+            self.controls
+                .lock()
+                .unwrap()
+                .push((control_code, send.to_vec()));
+            self.replies
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("no canned response left")
+        }
+    }
+
+    impl ApduTransport for Arc<MockTransport> {
+        fn transmit(&self, apdu: &[u8], response: &mut Vec<u8>) -> Result<(), PcscError> {
+            MockTransport::transmit(self, apdu, response)
+        }
+
+        fn control(&self, control_code: u32, send: &[u8]) -> Result<Vec<u8>, PcscError> {
+            MockTransport::control(self, control_code, send)
+        }
+    }
+}
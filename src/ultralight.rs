@@ -0,0 +1,107 @@
+//! MIFARE Ultralight C 2-key 3DES mutual authentication
+
+use des::cipher::generic_array::GenericArray;
+use des::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use des::TdesEde2;
+
+/// First AUTHENTICATE frame, asking the tag for its encrypted RndB
+pub(crate) const AUTHENTICATE: [u8; 2] = [0x1A, 0x00];
+/// Frame code of the second step and of the tag's first answer
+pub(crate) const AUTHENTICATE_CONTINUE: u8 = 0xAF;
+
+// CBC over 8 byte blocks starting from `iv`, returning the last ciphertext block as the next IV
+fn cbc(cipher: &TdesEde2, data: &mut [u8], iv: [u8; 8], encrypt: bool) -> [u8; 8] {
+    let mut chain = iv;
+    for block in data.chunks_exact_mut(8) {
+        let ciphertext: [u8; 8] = if encrypt {
+            block.iter_mut().zip(chain).for_each(|(b, c)| *b ^= c);
+            cipher.encrypt_block(GenericArray::from_mut_slice(block));
+            block.try_into().unwrap()
+        } else {
+            let ciphertext = block.try_into().unwrap();
+            cipher.decrypt_block(GenericArray::from_mut_slice(block));
+            block.iter_mut().zip(chain).for_each(|(b, c)| *b ^= c);
+            ciphertext
+        };
+        chain = ciphertext;
+    }
+    chain
+}
+
+/// Answer the tag's `ek(RndB)` challenge: returns `ek(RndA || RndB')` to send and the IV for
+/// decrypting the tag's reply
+pub(crate) fn challenge_response(
+    key: &[u8; 16],
+    ek_rnd_b: [u8; 8],
+    rnd_a: [u8; 8],
+) -> ([u8; 16], [u8; 8]) {
+    let cipher = TdesEde2::new(GenericArray::from_slice(key));
+    let mut rnd_b = ek_rnd_b;
+    let iv = cbc(&cipher, &mut rnd_b, [0; 8], false);
+    let mut token = [0; 16];
+    token[..8].copy_from_slice(&rnd_a);
+    token[8..].copy_from_slice(&rnd_b);
+    token[8..].rotate_left(1);
+    let iv = cbc(&cipher, &mut token, iv, true);
+    (token, iv)
+}
+
+/// Check the tag's `ek(RndA')` proves it holds the key
+pub(crate) fn verify_response(
+    key: &[u8; 16],
+    iv: [u8; 8],
+    ek_rnd_a: [u8; 8],
+    rnd_a: [u8; 8],
+) -> bool {
+    let cipher = TdesEde2::new(GenericArray::from_slice(key));
+    let mut rotated = ek_rnd_a;
+    cbc(&cipher, &mut rotated, iv, false);
+    rotated.rotate_right(1);
+    rotated == rnd_a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Factory key, "BREAKMEIFYOUCAN!" byte reversed per half
+    const KEY: [u8; 16] = *b"IEMKAERB!NACUOYF";
+
+    #[test]
+    fn challenge_response_bytes() {
+        let cipher = TdesEde2::new(GenericArray::from_slice(&KEY));
+        let rnd_a = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        let rnd_b = [0xA1, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7, 0xA8];
+        let mut ek_rnd_b = rnd_b;
+        cbc(&cipher, &mut ek_rnd_b, [0; 8], true);
+
+        let (token, iv) = challenge_response(&KEY, ek_rnd_b, rnd_a);
+        let mut plain = token;
+        cbc(&cipher, &mut plain, ek_rnd_b, false);
+        assert_eq!(plain[..8], rnd_a);
+        assert_eq!(plain[8..], [0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7, 0xA8, 0xA1]);
+        assert_eq!(iv, token[8..]);
+
+        let mut ek_rnd_a = [0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x01];
+        cbc(&cipher, &mut ek_rnd_a, iv, true);
+        assert!(verify_response(&KEY, iv, ek_rnd_a, rnd_a));
+        assert!(!verify_response(&KEY, iv, ek_rnd_a, rnd_b));
+    }
+
+    // Authentication example from the MF0ICU2 datasheet, run with the factory key
+    #[test]
+    fn datasheet_example() {
+        let ek_rnd_b = [0x57, 0x72, 0x93, 0xFD, 0x2F, 0x34, 0xCA, 0x51];
+        let rnd_a = [0xA8, 0xAF, 0x3B, 0x25, 0x6C, 0x75, 0xED, 0x40];
+        let (token, iv) = challenge_response(&KEY, ek_rnd_b, rnd_a);
+        assert_eq!(
+            token,
+            [
+                0x0A, 0x63, 0x85, 0x59, 0xFC, 0x77, 0x37, 0xF9, 0xF1, 0x5D, 0x78, 0x62, 0xEB, 0xBE,
+                0x96, 0x7A,
+            ]
+        );
+        let ek_rnd_a = [0x3B, 0x88, 0x4F, 0xA0, 0x7C, 0x13, 0x7C, 0xE1];
+        assert!(verify_response(&KEY, iv, ek_rnd_a, rnd_a));
+    }
+}
@@ -0,0 +1,20 @@
+#![cfg(feature = "tokio")]
+
+use nfc_pcsc::{AsyncReader, Pcsc};
+
+// Needs a reader attached and a card presented, so only runs with NFC_PCSC_HARDWARE set
+#[test]
+fn next_card_from_first_reader() {
+    if std::env::var_os("NFC_PCSC_HARDWARE").is_none() {
+        return;
+    }
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let mut pcsc = Pcsc::new().unwrap();
+    let reader = pcsc.get_readers().unwrap().into_iter().next().unwrap();
+    let mut reader = AsyncReader::new(reader);
+    let tag = runtime.block_on(reader.next_card()).unwrap();
+    assert!(!tag.atr_info().atr.is_empty());
+    assert!(!tag.uid_info().unwrap().uid.is_empty());
+}